@@ -0,0 +1,2 @@
+-- Drop the `party_snapshots` table
+DROP TABLE party_snapshots;
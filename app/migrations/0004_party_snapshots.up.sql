@@ -0,0 +1,8 @@
+-- Periodic snapshots of the derived party state, so indexing only replays events after the snapshot
+CREATE TABLE party_snapshots (
+    party_id TEXT NOT NULL REFERENCES parties(party_id),
+    event_id INTEGER NOT NULL,
+    state JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (party_id, event_id)
+);
@@ -0,0 +1,2 @@
+-- Store event data as TEXT again
+ALTER TABLE events ALTER COLUMN data TYPE TEXT USING data::text;
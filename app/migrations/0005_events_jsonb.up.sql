@@ -0,0 +1,2 @@
+-- Store event data as JSONB, rows written before this migration have no version envelope and are read as version 0
+ALTER TABLE events ALTER COLUMN data TYPE JSONB USING data::jsonb;
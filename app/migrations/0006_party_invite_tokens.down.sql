@@ -0,0 +1,2 @@
+-- Drop the `party_invites` table
+DROP TABLE party_invites;
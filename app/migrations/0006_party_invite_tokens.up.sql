@@ -0,0 +1,13 @@
+-- Invite tokens for a party, each with an optional expiry and use limit
+CREATE TABLE party_invites (
+    invite_id TEXT PRIMARY KEY,
+    party_id TEXT NOT NULL REFERENCES parties(party_id),
+    created_by TEXT NOT NULL REFERENCES users(user_id),
+    expires_at TIMESTAMP WITH TIME ZONE,
+    max_uses INTEGER,
+    uses INTEGER NOT NULL DEFAULT 0,
+    revoked BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX party_invites_party_id_idx ON party_invites (party_id);
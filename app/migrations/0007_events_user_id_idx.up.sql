@@ -0,0 +1,2 @@
+-- Look up the parties a user took part in
+CREATE INDEX events_user_id_idx ON events (user_id, party_id);
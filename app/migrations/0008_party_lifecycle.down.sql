@@ -0,0 +1,3 @@
+ALTER TABLE parties DROP COLUMN archived_at;
+ALTER TABLE parties DROP COLUMN cracked_code;
+ALTER TABLE parties DROP COLUMN cracked_at;
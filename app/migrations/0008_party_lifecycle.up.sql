@@ -0,0 +1,4 @@
+-- Track when a party was cracked and when it was archived
+ALTER TABLE parties ADD COLUMN cracked_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE parties ADD COLUMN cracked_code TEXT;
+ALTER TABLE parties ADD COLUMN archived_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,2 @@
+ALTER TABLE events DROP CONSTRAINT events_idempotency_key_unique;
+ALTER TABLE events DROP COLUMN idempotency_key;
@@ -0,0 +1,3 @@
+-- Client chosen key so retried submissions do not store the same event twice
+ALTER TABLE events ADD COLUMN idempotency_key TEXT;
+ALTER TABLE events ADD CONSTRAINT events_idempotency_key_unique UNIQUE (party_id, idempotency_key);
@@ -0,0 +1,14 @@
+-- Code lists uploaded by users, owned by either a user or a party
+CREATE TABLE code_lists (
+    list_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    user_id TEXT REFERENCES users(user_id),
+    party_id TEXT REFERENCES parties(party_id),
+    created_by TEXT NOT NULL REFERENCES users(user_id),
+    codes TEXT[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((user_id IS NULL) <> (party_id IS NULL))
+);
+
+CREATE INDEX code_lists_user_id_idx ON code_lists (user_id);
+CREATE INDEX code_lists_party_id_idx ON code_lists (party_id);
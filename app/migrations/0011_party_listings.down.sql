@@ -0,0 +1 @@
+DROP TABLE party_listings;
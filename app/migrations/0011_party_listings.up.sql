@@ -0,0 +1,27 @@
+-- Discovery settings of a party, kept next to the event log so listings don't have to scan it
+CREATE TABLE party_listings (
+    party_id TEXT PRIMARY KEY REFERENCES parties(party_id),
+    server_id TEXT,
+    private BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX party_listings_server_id_idx ON party_listings (server_id) WHERE NOT private;
+
+-- Take the latest value of each setting from the events stored so far
+WITH settings AS (
+    SELECT party_id, event_id, COALESCE(data->'data', data)->'party_setting_changed' AS setting
+    FROM events
+    WHERE COALESCE(data->'data', data) ? 'party_setting_changed'
+), servers AS (
+    SELECT DISTINCT ON (party_id) party_id, setting->>'value' AS server_id
+    FROM settings WHERE setting->>'setting' = 'server_id'
+    ORDER BY party_id, event_id DESC
+), private AS (
+    SELECT DISTINCT ON (party_id) party_id, setting->'value' = 'true'::jsonb AS private
+    FROM settings WHERE setting->>'setting' = 'private'
+    ORDER BY party_id, event_id DESC
+)
+INSERT INTO party_listings (party_id, server_id, private)
+SELECT COALESCE(s.party_id, pr.party_id), s.server_id, COALESCE(pr.private, FALSE)
+FROM servers s
+FULL JOIN private pr ON pr.party_id = s.party_id;
@@ -0,0 +1,29 @@
+-- Current members of a party, kept next to the event log so listings don't have to project every party
+CREATE TABLE party_members (
+    party_id TEXT NOT NULL REFERENCES parties(party_id),
+    user_id TEXT NOT NULL,
+    PRIMARY KEY (party_id, user_id)
+);
+
+CREATE INDEX party_members_user_id_idx ON party_members (user_id);
+
+-- A user is a member if the latest join, leave or kick about them was a join
+WITH changes AS (
+    SELECT party_id, event_id,
+        COALESCE(data->'data', data)->'user_join_leave'->>'user_id' AS user_id,
+        (COALESCE(data->'data', data)->'user_join_leave'->>'is_join')::boolean AS is_join
+    FROM events
+    WHERE COALESCE(data->'data', data) ? 'user_join_leave'
+    UNION ALL
+    SELECT party_id, event_id,
+        COALESCE(data->'data', data)->'party_member_kicked'->>'user_id' AS user_id,
+        FALSE AS is_join
+    FROM events
+    WHERE COALESCE(data->'data', data) ? 'party_member_kicked'
+), latest AS (
+    SELECT DISTINCT ON (party_id, user_id) party_id, user_id, is_join
+    FROM changes WHERE user_id IS NOT NULL
+    ORDER BY party_id, user_id, event_id DESC
+)
+INSERT INTO party_members (party_id, user_id)
+SELECT party_id, user_id FROM latest WHERE is_join;
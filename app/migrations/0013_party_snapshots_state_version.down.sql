@@ -0,0 +1 @@
+ALTER TABLE party_snapshots DROP COLUMN state_version;
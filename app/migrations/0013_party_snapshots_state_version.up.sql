@@ -0,0 +1,2 @@
+-- Version of the party state projection a snapshot was built by, snapshots taken so far count as version 0
+ALTER TABLE party_snapshots ADD COLUMN state_version INTEGER NOT NULL DEFAULT 0;
@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::time::Duration;
 
 use futures::future::join_all;
@@ -7,6 +6,7 @@ use moka::future::Cache;
 
 use crate::models::bm::player::BattleMetricsPlayerResponse;
 use crate::models::bm::recent::BattleMetricsRecentServers;
+use crate::models::codes::custom::CustomCodeList;
 use crate::models::party::PartyState;
 use crate::models::rm::MapResponse;
 use crate::models::rm::SearchResponse;
@@ -20,6 +20,8 @@ pub struct AppCache {
     pub rm_map: Cache<String, MapResponse>,
     pub scmm_total_inventory: Cache<String, SCMMTotalInventoryResponse>,
     pub party_state: Cache<String, PartyState>,
+    /// Uploaded code lists keyed by list id, they never change once uploaded
+    pub code_lists: Cache<String, CustomCodeList>,
 }
 
 impl AppCache {
@@ -27,38 +29,45 @@ impl AppCache {
         Self {
             bm_user_from_name: Cache::builder()
                 .time_to_live(Duration::from_secs(5 * 60))
-                .time_to_idle(Duration::from_secs(1 * 60))
+                .time_to_idle(Duration::from_secs(60))
                 .max_capacity(1000)
                 .build(),
             bm_recent_servers: Cache::builder()
                 .time_to_live(Duration::from_secs(5 * 60))
-                .time_to_idle(Duration::from_secs(1 * 60))
+                .time_to_idle(Duration::from_secs(60))
                 .max_capacity(1000)
                 .build(),
             rm_search: Cache::builder()
                 .time_to_live(Duration::from_secs(5 * 60))
-                .time_to_idle(Duration::from_secs(1 * 60))
+                .time_to_idle(Duration::from_secs(60))
                 .max_capacity(1000)
                 .build(),
             rm_map: Cache::builder()
                 .time_to_live(Duration::from_secs(5 * 60))
-                .time_to_idle(Duration::from_secs(1 * 60))
+                .time_to_idle(Duration::from_secs(60))
                 .max_capacity(1000)
                 .build(),
             scmm_total_inventory: Cache::builder()
                 .time_to_live(Duration::from_secs(5 * 60))
-                .time_to_idle(Duration::from_secs(1 * 60))
+                .time_to_idle(Duration::from_secs(60))
                 .max_capacity(1000)
                 .build(),
             party_state: Cache::builder()
                 .time_to_live(Duration::from_secs(5 * 60))
-                .time_to_idle(Duration::from_secs(1 * 60))
+                .time_to_idle(Duration::from_secs(60))
                 .max_capacity(1000)
                 .build(),
+            // weighed by the number of codes, lists range from a handful to many thousands of codes
+            code_lists: Cache::builder()
+                .time_to_live(Duration::from_secs(5 * 60))
+                .time_to_idle(Duration::from_secs(60))
+                .weigher(|_, list: &CustomCodeList| list.codes.len() as u32)
+                .max_capacity(1_000_000)
+                .build(),
         }
     }
 
-    pub async fn get_sizes(&self) -> (u64, u64, u64, u64, u64, u64) {
+    pub async fn get_sizes(&self) -> (u64, u64, u64, u64, u64, u64, u64) {
         (
             self.bm_user_from_name.weighted_size(),
             self.bm_recent_servers.weighted_size(),
@@ -66,6 +75,7 @@ impl AppCache {
             self.rm_map.weighted_size(),
             self.scmm_total_inventory.weighted_size(),
             self.party_state.weighted_size(),
+            self.code_lists.weighted_size(),
         )
     }
 
@@ -73,8 +83,8 @@ impl AppCache {
         loop {
             self.collect_all().await;
 
-            let (bm_user_from_name, bm_recent_servers, rm_search, rm_map, scmm_total_inventory, party_state) = self.get_sizes().await;
-            tracing::info!(bm_user_from_name, bm_recent_servers, rm_search, rm_map, scmm_total_inventory, party_state);
+            let (bm_user_from_name, bm_recent_servers, rm_search, rm_map, scmm_total_inventory, party_state, code_lists) = self.get_sizes().await;
+            tracing::info!(bm_user_from_name, bm_recent_servers, rm_search, rm_map, scmm_total_inventory, party_state, code_lists);
             async_std::task::sleep(std::time::Duration::from_secs(3*60)).await;
         }
     }
@@ -87,6 +97,7 @@ impl AppCache {
             async { self.rm_map.run_pending_tasks().await }.boxed(),
             async { self.scmm_total_inventory.run_pending_tasks().await }.boxed(),
             async { self.party_state.run_pending_tasks().await }.boxed(),
+            async { self.code_lists.run_pending_tasks().await }.boxed(),
         ];
 
         join_all(tasks).await;
@@ -1,4 +1,5 @@
 use async_std::prelude::FutureExt;
+use models::party::{snapshot::PartySnapshot, Party};
 use state::{AppState, AppStateInner};
 use std::sync::Arc;
 
@@ -26,5 +27,13 @@ async fn main() {
 
     let cache_size_notifier = state.cache.collect(&state);
 
-    cache_size_notifier.race(http).await;
+    let party_snapshots = PartySnapshot::collect(&state);
+
+    let party_archiver = Party::archive_idle_loop(&state);
+
+    cache_size_notifier
+        .race(party_snapshots)
+        .race(party_archiver)
+        .race(http)
+        .await;
 }
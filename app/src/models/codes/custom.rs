@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqids::Sqids;
+use sqlx::{PgExecutor, Postgres, Transaction};
+
+use super::{is_valid_code, CodeListOrderEntry, CodeRejection, CodesRejected, RejectedCode};
+use crate::state::AppState;
+
+/// A code list uploaded by a user, owned by either that user or a party
+/// Lists can not be changed after the upload, so parties walking them keep a stable sequence
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct CustomCodeList {
+    pub list_id: String,
+    pub name: String,
+    /// Set when the list belongs to a user
+    pub user_id: Option<String>,
+    /// Set when the list belongs to a party
+    pub party_id: Option<String>,
+    pub created_by: String,
+    pub codes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Metadata of a custom code list without the codes themselves
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct CustomCodeListSummary {
+    pub list_id: String,
+    pub name: String,
+    pub user_id: Option<String>,
+    pub party_id: Option<String>,
+    pub created_by: String,
+    pub size: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Who a custom list belongs to
+pub enum CustomCodeListOwner<'a> {
+    User(&'a str),
+    Party(&'a str),
+}
+
+impl CustomCodeList {
+    pub async fn create(
+        name: &str,
+        owner: CustomCodeListOwner<'_>,
+        created_by: &str,
+        codes: Vec<String>,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        let random_number = rand::rng().random_range(0..u64::MAX);
+        let list_id = Sqids::default().encode(&[3, random_number]).unwrap();
+
+        let (user_id, party_id) = match owner {
+            CustomCodeListOwner::User(user_id) => (Some(user_id), None),
+            CustomCodeListOwner::Party(party_id) => (None, Some(party_id)),
+        };
+
+        let list = sqlx::query_as!(
+            Self,
+            "INSERT INTO code_lists (list_id, name, user_id, party_id, created_by, codes) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            list_id,
+            name,
+            user_id,
+            party_id,
+            created_by,
+            &codes
+        )
+        .fetch_one(&state.database.pool)
+        .await?;
+
+        Ok(list)
+    }
+
+    pub async fn get_by_id(list_id: &str, state: &AppState) -> Result<Option<Self>, sqlx::Error> {
+        if let Some(list) = state.cache.code_lists.get(list_id).await {
+            return Ok(Some(list));
+        }
+
+        let list = sqlx::query_as!(Self, "SELECT * FROM code_lists WHERE list_id = $1", list_id)
+            .fetch_optional(&state.database.pool)
+            .await?;
+
+        if let Some(list) = &list {
+            state
+                .cache
+                .code_lists
+                .insert(list.list_id.clone(), list.clone())
+                .await;
+        }
+
+        Ok(list)
+    }
+
+    /// Lists of a user or a party, newest first
+    pub async fn get_by_owner(
+        owner: CustomCodeListOwner<'_>,
+        state: &AppState,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let lists = match owner {
+            CustomCodeListOwner::User(user_id) => {
+                sqlx::query_as!(
+                    Self,
+                    "SELECT * FROM code_lists WHERE user_id = $1 ORDER BY created_at DESC",
+                    user_id
+                )
+                .fetch_all(&state.database.pool)
+                .await?
+            }
+            CustomCodeListOwner::Party(party_id) => {
+                sqlx::query_as!(
+                    Self,
+                    "SELECT * FROM code_lists WHERE party_id = $1 ORDER BY created_at DESC",
+                    party_id
+                )
+                .fetch_all(&state.database.pool)
+                .await?
+            }
+        };
+
+        Ok(lists)
+    }
+
+    /// The custom lists a list order refers to, keyed by list id
+    pub async fn get_for_order(
+        order: &[CodeListOrderEntry],
+        state: &AppState,
+    ) -> Result<HashMap<String, Self>, sqlx::Error> {
+        CustomCodeList::fetch_for_order(&state.database.pool, order, state).await
+    }
+
+    /// `get_for_order` on the connection of a transaction, for callers holding the party lock
+    pub async fn get_for_order_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        order: &[CodeListOrderEntry],
+        state: &AppState,
+    ) -> Result<HashMap<String, Self>, sqlx::Error> {
+        CustomCodeList::fetch_for_order(&mut **tx, order, state).await
+    }
+
+    async fn fetch_for_order(
+        executor: impl PgExecutor<'_>,
+        order: &[CodeListOrderEntry],
+        state: &AppState,
+    ) -> Result<HashMap<String, Self>, sqlx::Error> {
+        let mut lists = HashMap::new();
+        let mut missing = Vec::new();
+
+        for list_id in order.iter().filter_map(|entry| entry.list_id.as_ref()) {
+            match state.cache.code_lists.get(list_id).await {
+                Some(list) => {
+                    lists.insert(list_id.clone(), list);
+                }
+                None => missing.push(list_id.clone()),
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(lists);
+        }
+
+        let fetched = sqlx::query_as!(
+            Self,
+            "SELECT * FROM code_lists WHERE list_id = ANY($1)",
+            &missing
+        )
+        .fetch_all(executor)
+        .await?;
+
+        for list in fetched {
+            state
+                .cache
+                .code_lists
+                .insert(list.list_id.clone(), list.clone())
+                .await;
+            lists.insert(list.list_id.clone(), list);
+        }
+
+        Ok(lists)
+    }
+
+    /// Members of a party can use lists of the party and their own lists
+    pub fn usable_by(&self, party_id: &str, user_id: &str) -> bool {
+        self.party_id.as_deref() == Some(party_id) || self.user_id.as_deref() == Some(user_id)
+    }
+
+    pub fn summary(&self) -> CustomCodeListSummary {
+        CustomCodeListSummary {
+            list_id: self.list_id.clone(),
+            name: self.name.clone(),
+            user_id: self.user_id.clone(),
+            party_id: self.party_id.clone(),
+            created_by: self.created_by.clone(),
+            size: self.codes.len() as u32,
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// Codes of a plaintext upload, one per line
+pub fn parse_plain_text(body: &str) -> Vec<String> {
+    body.lines()
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Codes of a csv upload, taken from the first column
+/// A first row of `code` or `codes` is taken as the header and skipped
+/// Any other first row is kept, so an invalid one rejects the upload
+pub fn parse_csv(body: &str) -> Vec<String> {
+    let mut codes: Vec<String> = body
+        .lines()
+        .filter_map(|line| line.split(',').next())
+        .map(|field| field.trim().trim_matches('"').trim())
+        .filter(|field| !field.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if codes.first().is_some_and(|first| is_csv_header(first)) {
+        codes.remove(0);
+    }
+
+    codes
+}
+
+fn is_csv_header(field: &str) -> bool {
+    field.eq_ignore_ascii_case("code") || field.eq_ignore_ascii_case("codes")
+}
+
+/// Dedupe uploaded codes, keeping their order, and reject the upload if any of them is not a PIN
+pub fn validate_codes(codes: Vec<String>) -> Result<Vec<String>, CodesRejected> {
+    let invalid: Vec<_> = codes
+        .iter()
+        .filter(|code| !is_valid_code(code))
+        .map(|code| RejectedCode {
+            code: code.clone(),
+            reason: CodeRejection::Invalid,
+        })
+        .collect();
+
+    if !invalid.is_empty() {
+        return Err(CodesRejected::new("Codes must be 4 digit PINs", invalid));
+    }
+
+    let mut seen = HashSet::new();
+    let codes: Vec<String> = codes
+        .into_iter()
+        .filter(|code| seen.insert(code.clone()))
+        .collect();
+
+    if codes.is_empty() {
+        return Err(CodesRejected::new("No codes in the list", vec![]));
+    }
+
+    Ok(codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_takes_the_first_column() {
+        assert_eq!(
+            parse_csv("1234,a\n\"5678\",b\n\n 0000 \n"),
+            ["1234", "5678", "0000"]
+        );
+    }
+
+    #[test]
+    fn csv_skips_a_code_header() {
+        assert_eq!(parse_csv("Code,note\n1234,a\n"), ["1234"]);
+        assert_eq!(parse_csv("\"codes\"\n1234\n"), ["1234"]);
+    }
+
+    #[test]
+    fn csv_keeps_an_invalid_first_row() {
+        let codes = parse_csv("12a4\n1234\n");
+        assert_eq!(codes, ["12a4", "1234"]);
+
+        let rejected = validate_codes(codes).unwrap_err();
+        assert_eq!(rejected.rejected.len(), 1);
+        assert_eq!(rejected.rejected[0].code, "12a4");
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+
+use custom::CustomCodeList;
+use lazy_static::lazy_static;
+use poem::{http::StatusCode, IntoResponse};
+use poem_openapi::{payload::Json, Enum, Object};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use crate::state::AppState;
+
+pub mod custom;
+
+/// A named, ordered list of lock codes to work through
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct CodeList {
+    pub name: String,
+    pub description: Option<String>,
+    pub source: Option<String>,
+    pub size: u32,
+    pub codes: Vec<String>,
+}
+
+/// Metadata of a code list without the codes themselves
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct CodeListSummary {
+    pub name: String,
+    pub description: Option<String>,
+    pub source: Option<String>,
+    pub size: u32,
+}
+
+/// A single entry of a party's list order
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct CodeListOrderEntry {
+    /// Name of the list in the catalog
+    pub name: String,
+    /// Whether to walk the list back to front
+    pub reverse: bool,
+    /// Id of an uploaded list to walk instead of a catalog list, `name` is then only for display
+    #[serde(default)]
+    pub list_id: Option<String>,
+}
+
+lazy_static! {
+    /// The built-in code lists, in their default order
+    pub static ref CODE_LISTS: Vec<CodeList> = vec![
+        CodeList::new(
+            "Sequential Numbers",
+            Some("These are the sequential numbers from 0000 to 9999"),
+            None,
+            include_str!("./lists/math.txt"),
+        ),
+        CodeList::new("Common Years", None, None, include_str!("./lists/random.txt")),
+        CodeList::new(
+            "Angel Numbers",
+            Some("My gf found these on the internet. I don't know if they work. Good luck!"),
+            None,
+            include_str!("./lists/angel.txt"),
+        ),
+        CodeList::new(
+            "Rusttips 10.000",
+            Some("This is the original 10.000 codes list from Rusttips.com"),
+            Some("https://rusttips.com/top-10000-rust-door-lock-codes/"),
+            include_str!("./lists/rusttips.txt"),
+        ),
+    ];
+}
+
+impl CodeList {
+    fn new(name: &str, description: Option<&str>, source: Option<&str>, codes: &str) -> Self {
+        let codes: Vec<String> = codes
+            .lines()
+            .map(str::trim)
+            .filter(|code| !code.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            source: source.map(str::to_string),
+            size: codes.len() as u32,
+            codes,
+        }
+    }
+
+    pub fn all() -> &'static [CodeList] {
+        &CODE_LISTS
+    }
+
+    pub fn get_by_name(name: &str) -> Option<&'static CodeList> {
+        CODE_LISTS.iter().find(|list| list.name == name)
+    }
+
+    pub fn summary(&self) -> CodeListSummary {
+        CodeListSummary {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            source: self.source.clone(),
+            size: self.size,
+        }
+    }
+}
+
+impl CodeListOrderEntry {
+    /// The order used by parties that never changed it
+    pub fn default_order() -> Vec<CodeListOrderEntry> {
+        CODE_LISTS
+            .iter()
+            .map(|list| CodeListOrderEntry {
+                name: list.name.clone(),
+                reverse: false,
+                list_id: None,
+            })
+            .collect()
+    }
+}
+
+/// Walk the lists in the given order and keep only the first occurrence of every code
+/// `custom` holds the uploaded lists the order refers to, keyed by list id
+pub fn build_code_sequence(
+    order: &[CodeListOrderEntry],
+    custom: &HashMap<String, CustomCodeList>,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut sequence = Vec::new();
+
+    for entry in order {
+        let list_codes = match &entry.list_id {
+            Some(list_id) => custom.get(list_id).map(|list| &list.codes),
+            None => CodeList::get_by_name(&entry.name).map(|list| &list.codes),
+        };
+
+        let Some(list_codes) = list_codes else {
+            continue;
+        };
+
+        let codes: Box<dyn Iterator<Item = &String>> = if entry.reverse {
+            Box::new(list_codes.iter().rev())
+        } else {
+            Box::new(list_codes.iter())
+        };
+
+        for code in codes {
+            if seen.insert(code.as_str()) {
+                sequence.push(code.clone());
+            }
+        }
+    }
+
+    sequence
+}
+
+/// Build the sequence of a list order, loading the uploaded lists it refers to
+pub async fn load_code_sequence(
+    order: &[CodeListOrderEntry],
+    state: &AppState,
+) -> Result<Vec<String>, sqlx::Error> {
+    let custom = CustomCodeList::get_for_order(order, state).await?;
+
+    Ok(build_code_sequence(order, &custom))
+}
+
+/// `load_code_sequence` on the connection of a transaction, for callers holding the party lock
+pub async fn load_code_sequence_in_tx(
+    tx: &mut Transaction<'_, Postgres>,
+    order: &[CodeListOrderEntry],
+    state: &AppState,
+) -> Result<Vec<String>, sqlx::Error> {
+    let custom = CustomCodeList::get_for_order_in_tx(tx, order, state).await?;
+
+    Ok(build_code_sequence(order, &custom))
+}
+
+/// Rust code locks take a 4 digit PIN
+pub fn is_valid_code(code: &str) -> bool {
+    code.len() == 4 && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum CodeRejection {
+    /// Not a 4 digit PIN
+    Invalid,
+    /// Past the submission limit
+    TooMany,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct RejectedCode {
+    pub code: String,
+    pub reason: CodeRejection,
+}
+
+/// Body of the 422 returned when submitted or uploaded codes are refused, nothing of it is stored
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct CodesRejected {
+    pub message: String,
+    pub rejected: Vec<RejectedCode>,
+}
+
+impl CodesRejected {
+    pub fn new(message: &str, rejected: Vec<RejectedCode>) -> Self {
+        Self {
+            message: message.to_string(),
+            rejected,
+        }
+    }
+}
+
+impl From<CodesRejected> for poem::Error {
+    fn from(rejected: CodesRejected) -> Self {
+        poem::Error::from_response(
+            Json(rejected)
+                .with_status(StatusCode::UNPROCESSABLE_ENTITY)
+                .into_response(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_codes_are_4_digit_pins() {
+        for code in ["0000", "1234", "9999"] {
+            assert!(is_valid_code(code), "{}", code);
+        }
+
+        for code in ["", "123", "12345", "12a4", "-123", "+123", " 123", "١٢٣٤"] {
+            assert!(!is_valid_code(code), "{}", code);
+        }
+    }
+}
@@ -3,3 +3,4 @@ pub mod bm;
 pub mod rm;
 pub mod scmm;
 pub mod party;
+pub mod codes;
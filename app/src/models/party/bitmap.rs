@@ -0,0 +1,265 @@
+use std::{fmt, ops::BitOrAssign};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::models::codes::is_valid_code;
+
+/// Number of PINs a code lock accepts, `0000` up to `9999`
+pub const CODE_COUNT: usize = 10_000;
+
+/// Size of a serialized `CodeBitmap`
+pub const CODE_BITMAP_BYTES: usize = CODE_COUNT / 8;
+
+const WORDS: usize = CODE_COUNT.div_ceil(64);
+
+/// Set of codes with one bit per possible PIN
+///
+/// Serialized as base64 of `CODE_BITMAP_BYTES` bytes, bit `n % 8` (least significant first)
+/// of byte `n / 8` is set when code `n` is in the set.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CodeBitmap {
+    words: [u64; WORDS],
+}
+
+impl CodeBitmap {
+    pub fn new() -> Self {
+        Self { words: [0; WORDS] }
+    }
+
+    fn index(code: &str) -> Option<usize> {
+        if !is_valid_code(code) {
+            return None;
+        }
+
+        code.parse().ok()
+    }
+
+    /// Add a code to the set, codes that are not a PIN are ignored
+    /// Returns whether the code was not in the set yet
+    pub fn insert(&mut self, code: &str) -> bool {
+        let Some(index) = Self::index(code) else {
+            return false;
+        };
+
+        let bit = 1 << (index % 64);
+        let word = &mut self.words[index / 64];
+        let inserted = *word & bit == 0;
+        *word |= bit;
+
+        inserted
+    }
+
+    pub fn contains(&self, code: &str) -> bool {
+        Self::index(code).is_some_and(|index| self.words[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// Number of codes in the set
+    pub fn count(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// Number of codes in both sets
+    pub fn intersection_count(&self, other: &Self) -> u32 {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .map(|(a, b)| (a & b).count_ones())
+            .sum()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .take(CODE_BITMAP_BYTES)
+            .collect()
+    }
+
+    /// Read a bitmap written by `to_bytes`, `None` if the length is off
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != CODE_BITMAP_BYTES {
+            return None;
+        }
+
+        let mut bitmap = Self::new();
+
+        for (word, chunk) in bitmap.words.iter_mut().zip(bytes.chunks(8)) {
+            let mut buf = [0; 8];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            *word = u64::from_le_bytes(buf);
+        }
+
+        Some(bitmap)
+    }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.to_bytes())
+    }
+
+    pub fn from_base64(encoded: &str) -> Option<Self> {
+        STANDARD
+            .decode(encoded)
+            .ok()
+            .and_then(|bytes| Self::from_bytes(&bytes))
+    }
+}
+
+impl Default for CodeBitmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CodeBitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CodeBitmap({} codes)", self.count())
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for CodeBitmap {
+    fn from_iter<I: IntoIterator<Item = S>>(codes: I) -> Self {
+        let mut bitmap = Self::new();
+
+        for code in codes {
+            bitmap.insert(code.as_ref());
+        }
+
+        bitmap
+    }
+}
+
+impl<S: AsRef<str>> Extend<S> for CodeBitmap {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, codes: I) {
+        for code in codes {
+            self.insert(code.as_ref());
+        }
+    }
+}
+
+/// Union
+impl BitOrAssign<&CodeBitmap> for CodeBitmap {
+    fn bitor_assign(&mut self, other: &CodeBitmap) {
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a |= b;
+        }
+    }
+}
+
+impl Serialize for CodeBitmap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_base64())
+    }
+}
+
+impl<'de> Deserialize<'de> for CodeBitmap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+
+        Self::from_base64(&encoded).ok_or_else(|| {
+            de::Error::custom(format!("expected base64 of {} bytes", CODE_BITMAP_BYTES))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialized_layout() {
+        let bitmap: CodeBitmap = ["0000", "0007", "9999"].into_iter().collect();
+        let bytes = bitmap.to_bytes();
+
+        assert_eq!(bytes.len(), CODE_BITMAP_BYTES);
+        assert_eq!(bytes.len(), 1250);
+        // code n is bit n % 8 of byte n / 8, least significant first
+        assert_eq!(bytes[0], 0b1000_0001);
+        assert_eq!(bytes[1249], 0b1000_0000);
+        assert!(bytes[1..1249].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn single_code_bytes() {
+        for (code, index, byte) in [
+            ("0000", 0, 0x01),
+            ("0007", 0, 0x80),
+            ("0008", 1, 0x01),
+            ("9999", 1249, 0x80),
+        ] {
+            let bytes = CodeBitmap::from_iter([code]).to_bytes();
+
+            let mut expected = vec![0; CODE_BITMAP_BYTES];
+            expected[index] = byte;
+            assert_eq!(bytes, expected, "code {}", code);
+        }
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let bitmap: CodeBitmap = ["0000", "1234", "4321", "9998", "9999"]
+            .into_iter()
+            .collect();
+        let encoded = bitmap.to_base64();
+
+        assert_eq!(STANDARD.decode(&encoded).unwrap().len(), CODE_BITMAP_BYTES);
+
+        let decoded = CodeBitmap::from_base64(&encoded).unwrap();
+        assert_eq!(decoded, bitmap);
+        assert_eq!(decoded.count(), 5);
+        assert!(decoded.contains("1234"));
+        assert!(!decoded.contains("1235"));
+
+        let json = serde_json::to_string(&bitmap).unwrap();
+        assert_eq!(serde_json::from_str::<CodeBitmap>(&json).unwrap(), bitmap);
+    }
+
+    #[test]
+    fn trailing_partial_word() {
+        // the last word only carries codes 9984 to 9999, the bytes past them are not serialized
+        let mut bytes = vec![0; CODE_BITMAP_BYTES];
+        bytes[1248] = 0x01;
+        bytes[1249] = 0xff;
+
+        let bitmap = CodeBitmap::from_bytes(&bytes).unwrap();
+        assert_eq!(bitmap.count(), 9);
+        assert!(bitmap.contains("9984"));
+        assert!(!bitmap.contains("9985"));
+        assert!(bitmap.contains("9992"));
+        assert!(bitmap.contains("9999"));
+        assert_eq!(bitmap.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn rejects_other_lengths() {
+        assert!(CodeBitmap::from_bytes(&[0; CODE_BITMAP_BYTES - 1]).is_none());
+        assert!(CodeBitmap::from_bytes(&[0; CODE_BITMAP_BYTES + 1]).is_none());
+        assert!(CodeBitmap::from_base64("AAAA").is_none());
+        assert!(CodeBitmap::from_base64("not base64").is_none());
+        assert!(serde_json::from_str::<CodeBitmap>("\"AAAA\"").is_err());
+    }
+
+    #[test]
+    fn insert_ignores_invalid_codes() {
+        let mut bitmap = CodeBitmap::new();
+
+        assert!(bitmap.insert("0420"));
+        assert!(!bitmap.insert("0420"));
+        assert!(!bitmap.insert("420"));
+        assert!(!bitmap.insert("10000"));
+        assert!(!bitmap.insert("12a4"));
+        assert_eq!(bitmap.count(), 1);
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let mut a: CodeBitmap = ["0001", "0002", "9999"].into_iter().collect();
+        let b: CodeBitmap = ["0002", "9999", "5000"].into_iter().collect();
+
+        assert_eq!(a.intersection_count(&b), 2);
+
+        a |= &b;
+        assert_eq!(a.count(), 4);
+        assert!(a.contains("5000"));
+    }
+}
@@ -1,19 +1,33 @@
 use chrono::{DateTime, Utc};
-use poem_openapi::{Object, Union};
+use poem_openapi::{Enum, Object, Union};
 use serde::{Deserialize, Serialize};
-use serde_json;
-use sqlx::prelude::FromRow;
+use sqlx::{prelude::FromRow, PgExecutor, Postgres, Transaction};
 
-use crate::state::AppState;
+use super::{
+    lock::default_lock_id,
+    role::PartyRole,
+    settings::{PartySetting, PartySettingKey},
+    Party,
+};
+pub use crate::models::codes::{CodeRejection, CodesRejected, RejectedCode};
+use crate::{
+    models::codes::{is_valid_code, CodeList, CodeListOrderEntry},
+    state::AppState,
+};
+
+/// Most distinct codes a single `PartyCodesSubmitted` event can carry
+pub const MAX_SUBMITTED_CODES: usize = 100;
 
 // Domain model for use in the application code
-#[derive(Debug, Serialize, Deserialize, Object)]
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEvent {
     pub party_id: String,
     pub event_id: i32,
     pub user_id: String,
     pub data: PartyEventData,
     pub created_at: DateTime<Utc>,
+    /// Key the event was submitted with, lets clients match live events to their own submissions
+    pub idempotency_key: Option<String>,
 }
 
 // Database model that directly maps to the database schema
@@ -22,101 +36,406 @@ struct PartyEventDb {
     pub party_id: String,
     pub event_id: i32,
     pub user_id: String,
-    pub data: String, // Store JSON as text in the database
+    pub data: serde_json::Value, // Stored as a versioned `PartyEventEnvelope`
     pub created_at: DateTime<Utc>,
+    pub idempotency_key: Option<String>,
+}
+
+/// Version of the stored event shape, bump this and add a step to `upcast` when changing an event
+pub const PARTY_EVENT_VERSION: u32 = 2;
+
+/// Envelope the event data is stored in
+#[derive(Debug, Serialize, Deserialize)]
+struct PartyEventEnvelope {
+    version: u32,
+    data: serde_json::Value,
 }
 
-impl From<PartyEventDb> for PartyEvent {
-    fn from(db: PartyEventDb) -> Self {
-        Self {
+impl TryFrom<PartyEventDb> for PartyEvent {
+    type Error = sqlx::Error;
+
+    fn try_from(db: PartyEventDb) -> Result<Self, Self::Error> {
+        // Rows written before the envelope existed hold the bare event data
+        let envelope = match db.data.get("version") {
+            Some(_) => serde_json::from_value::<PartyEventEnvelope>(db.data),
+            None => Ok(PartyEventEnvelope {
+                version: 0,
+                data: db.data,
+            }),
+        }
+        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+        let data = serde_json::from_value(upcast(envelope.version, envelope.data, &db.user_id))
+            .map_err(|e| {
+                tracing::error!("Failed to decode event {}: {}", db.event_id, e);
+                sqlx::Error::Decode(e.into())
+            })?;
+
+        Ok(Self {
             party_id: db.party_id,
             event_id: db.event_id,
             user_id: db.user_id,
-            data: serde_json::from_str(&db.data)
-                .expect("Failed to deserialize PartyEventData from JSON"),
+            data,
             created_at: db.created_at,
+            idempotency_key: db.idempotency_key,
+        })
+    }
+}
+
+/// Bring stored event data up to `PARTY_EVENT_VERSION`, one version at a time
+fn upcast(version: u32, mut data: serde_json::Value, user_id: &str) -> serde_json::Value {
+    if version < 1 {
+        // v0 -> v1: clients filled `user_id` with "deprecated", use the author of the event instead
+        for tag in ["user_codes_submitted", "user_cursor_update"] {
+            if let Some(event) = data.get_mut(tag).and_then(|e| e.as_object_mut()) {
+                let deprecated = event
+                    .get("user_id")
+                    .and_then(|user_id| user_id.as_str())
+                    .is_none_or(|user_id| user_id == "deprecated");
+
+                if deprecated {
+                    event.insert("user_id".to_string(), user_id.into());
+                }
+            }
+        }
+
+        // v0 -> v1: list order entries could be missing `reverse`
+        if let Some(order) = data
+            .get_mut("party_list_order_changed")
+            .and_then(|e| e.get_mut("order"))
+            .and_then(|order| order.as_array_mut())
+        {
+            for entry in order.iter_mut().filter_map(|entry| entry.as_object_mut()) {
+                entry.entry("reverse").or_insert(false.into());
+            }
+        }
+    }
+
+    if version < 2 {
+        // v1 -> v2: settings became typed, settings outside of the schema move to their own event
+        let unknown_setting = data
+            .get("party_setting_changed")
+            .and_then(|e| e.get("setting"))
+            .is_some_and(|setting| {
+                serde_json::from_value::<PartySettingKey>(setting.clone()).is_err()
+            });
+
+        if unknown_setting {
+            if let Some(event) = data
+                .as_object_mut()
+                .and_then(|data| data.remove("party_setting_changed"))
+            {
+                data = serde_json::json!({ "party_legacy_setting_changed": event });
+            }
         }
     }
+
+    data
 }
 
-#[derive(Debug, Serialize, Deserialize, Union)]
-#[oai(discriminator_name = "type")]
-pub enum PartyEventData {
-    #[serde(rename = "party_created")]
-    PartyCreated(PartyEventCreated),
-    #[serde(rename = "party_owner_changed")]
-    PartyOwnerChanged(PartyEventOwnerChanged),
-    #[serde(rename = "user_join_leave")]
-    PartyJoinLeave(PartyEventJoinLeave),
-    #[serde(rename = "user_codes_submitted")]
-    PartyCodesSubmitted(PartyEventCodesSubmitted),
-    #[serde(rename = "user_cursor_update")]
-    PartyCursorUpdate(PartyEventCursorUpdate),
-    #[serde(rename = "user_chat_message")]
-    PartyChatMessage(PartyEventChatMessage),
-    #[serde(rename = "party_list_order_changed")]
-    PartyListOrderChanged(PartyEventListOrderChanged),
-    #[serde(rename = "party_setting_changed")]
-    PartySettingChanged(PartyEventSettingChanged),
+/// Declares `PartyEventData` and the matching `PartyEventType` from a single list,
+/// each event is stored under its key in the versioned envelope
+macro_rules! party_events {
+    ($($key:literal => $variant:ident($data:ty),)*) => {
+        #[derive(Debug, Clone, Serialize, Deserialize, Union)]
+        #[oai(discriminator_name = "type")]
+        pub enum PartyEventData {
+            $(
+                #[serde(rename = $key)]
+                $variant($data),
+            )*
+        }
+
+        /// Event types as they appear in the `type` field, used to filter event queries
+        /// Serializes to the key the event is stored under
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+        pub enum PartyEventType {
+            $(
+                #[serde(rename = $key)]
+                $variant,
+            )*
+        }
+
+        impl PartyEventType {
+            pub fn storage_key(&self) -> &'static str {
+                match self {
+                    $(PartyEventType::$variant => $key,)*
+                }
+            }
+        }
+    };
+}
+
+party_events! {
+    "party_created" => PartyCreated(PartyEventCreated),
+    "party_owner_changed" => PartyOwnerChanged(PartyEventOwnerChanged),
+    "user_join_leave" => PartyJoinLeave(PartyEventJoinLeave),
+    "user_codes_submitted" => PartyCodesSubmitted(PartyEventCodesSubmitted),
+    "user_cursor_update" => PartyCursorUpdate(PartyEventCursorUpdate),
+    "user_codes_assigned" => PartyCodesAssigned(PartyEventCodesAssigned),
+    "user_chat_message" => PartyChatMessage(PartyEventChatMessage),
+    "party_list_order_changed" => PartyListOrderChanged(PartyEventListOrderChanged),
+    "party_setting_changed" => PartySettingChanged(PartyEventSettingChanged),
+    "party_secret_rotated" => PartySecretRotated(PartyEventSecretRotated),
+    "party_role_changed" => PartyRoleChanged(PartyEventRoleChanged),
+    "party_member_kicked" => PartyMemberKicked(PartyEventMemberKicked),
+    "party_legacy_setting_changed" => PartyLegacySettingChanged(PartyEventLegacySettingChanged),
+    "party_archived" => PartyArchived(PartyEventArchived),
+    "party_code_found" => PartyCodeFound(PartyEventCodeFound),
+    "party_lock_created" => PartyLockCreated(PartyEventLockCreated),
+}
+
+/// Which events of a party to page through
+#[derive(Debug, Default)]
+pub struct PartyEventFilter {
+    /// Only events after this id, oldest first
+    pub after: Option<i32>,
+    /// Only events before this id, newest first, takes precedence over `after`
+    pub before: Option<i32>,
+    /// Only events created by this user
+    pub user_id: Option<String>,
+    /// Only events of these types, all types if empty
+    pub types: Vec<PartyEventType>,
 }
 
 impl PartyEventData {
-    pub fn requires_cache_invalidation(&self) -> bool {
-        matches!(self, PartyEventData::PartySettingChanged(_) | PartyEventData::PartyJoinLeave(_))
+    /// Check the event payload before it is stored
+    pub fn validate(&self) -> poem::Result<()> {
+        match self {
+            PartyEventData::PartyCodesSubmitted(event) => event.validate(),
+            PartyEventData::PartyListOrderChanged(event) => event.validate(),
+            PartyEventData::PartySettingChanged(event) => event.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the event takes `user_id` out of the party, by leaving or being kicked
+    pub fn removes_member(&self, user_id: &str) -> bool {
+        match self {
+            PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                user_id: member,
+                is_join: false,
+                ..
+            }) => member == user_id,
+            PartyEventData::PartyMemberKicked(PartyEventMemberKicked {
+                user_id: member, ..
+            }) => member == user_id,
+            _ => false,
+        }
     }
 }
 
 /// Auto generated event when a party is created
-#[derive(Debug, Serialize, Deserialize, Object)]
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventCreated {
     pub owner_id: String,
 }
 
-/// TODO: This event is unimplemented at this time
-#[derive(Debug, Serialize, Deserialize, Object)]
+/// Emitted when ownership of the party is handed to another member
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventOwnerChanged {
     pub owner_id: String,
 }
 
-/// TODO: This event is unimplemented at this time
-#[derive(Debug, Serialize, Deserialize, Object)]
+/// Emitted when a user joins or leaves the party
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventJoinLeave {
     pub user_id: String,
     pub is_join: bool,
+    /// Invite that was redeemed to join, if any
+    pub invite_id: Option<String>,
 }
 
-/// TODO: This event is unimplemented at this time
-#[derive(Debug, Serialize, Deserialize, Object)]
+/// Codes a member tried on a lock
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventCodesSubmitted {
+    /// Filled in by the server with the submitting user, any value sent by clients is ignored
+    #[serde(default)]
+    #[oai(default)]
     pub user_id: String,
+    /// 4 digit PINs, duplicates are dropped
     pub codes: Vec<String>,
+    /// Lock the codes were tried on
+    #[serde(default = "default_lock_id")]
+    #[oai(default = "default_lock_id")]
+    pub lock_id: String,
+}
+
+impl PartyEventCodesSubmitted {
+    /// Drop repeated codes, keeping the order they were first submitted in
+    pub fn dedupe(&mut self) {
+        let mut seen = std::collections::HashSet::new();
+
+        self.codes.retain(|code| seen.insert(code.clone()));
+    }
+
+    /// Every code has to be a PIN and a submission can not exceed `MAX_SUBMITTED_CODES`
+    pub fn validate(&self) -> poem::Result<()> {
+        if self.codes.is_empty() {
+            return Err(CodesRejected::new("No codes submitted", vec![]).into());
+        }
+
+        let invalid: Vec<_> = self
+            .codes
+            .iter()
+            .filter(|code| !is_valid_code(code))
+            .map(|code| RejectedCode {
+                code: code.clone(),
+                reason: CodeRejection::Invalid,
+            })
+            .collect();
+
+        if !invalid.is_empty() {
+            return Err(CodesRejected::new("Codes must be 4 digit PINs", invalid).into());
+        }
+
+        if self.codes.len() > MAX_SUBMITTED_CODES {
+            let over = self.codes[MAX_SUBMITTED_CODES..]
+                .iter()
+                .map(|code| RejectedCode {
+                    code: code.clone(),
+                    reason: CodeRejection::TooMany,
+                })
+                .collect();
+
+            return Err(CodesRejected::new(
+                &format!(
+                    "At most {} codes can be submitted at once",
+                    MAX_SUBMITTED_CODES
+                ),
+                over,
+            )
+            .into());
+        }
+
+        Ok(())
+    }
 }
 
-/// TODO: This event is unimplemented at this time
-#[derive(Debug, Serialize, Deserialize, Object)]
+/// Deprecated: clients should request work through `/party/:party_id/assign` instead
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventCursorUpdate {
     pub user_id: String,
     pub cursor: String,
     pub size: u32,
+    #[serde(default = "default_lock_id")]
+    #[oai(default = "default_lock_id")]
+    pub lock_id: String,
+}
+
+/// Emitted by the server when a batch of codes is leased to a user
+/// A new assignment replaces the user's previous lease
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventCodesAssigned {
+    pub user_id: String,
+    pub codes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default = "default_lock_id")]
+    #[oai(default = "default_lock_id")]
+    pub lock_id: String,
 }
 
 /// Represents a chat message sent by a user in the party
-#[derive(Debug, Serialize, Deserialize, Object)]
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventChatMessage {
     pub message: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Object)]
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventListOrderChanged {
-    pub order: Vec<serde_json::Value>,
+    pub order: Vec<CodeListOrderEntry>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Object)]
+impl PartyEventListOrderChanged {
+    /// Every catalog entry has to point at a list in the catalog
+    /// Uploaded lists are checked by the caller, see `CustomCodeList::usable_by`
+    pub fn validate(&self) -> poem::Result<()> {
+        for entry in &self.order {
+            if entry.list_id.is_none() && CodeList::get_by_name(&entry.name).is_none() {
+                return Err(poem::Error::from_string(
+                    format!("Unknown code list: {}", entry.name),
+                    poem::http::StatusCode::BAD_REQUEST,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Emitted when a party setting changes, the shape of `value` depends on `setting`
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
 pub struct PartyEventSettingChanged {
+    pub setting: PartySettingKey,
+    pub value: serde_json::Value,
+}
+
+impl PartyEventSettingChanged {
+    /// The value has to match the type of the setting
+    pub fn validate(&self) -> poem::Result<()> {
+        PartySetting::try_from(self)
+            .map_err(|e| {
+                poem::Error::from_string(
+                    format!("Invalid value for setting: {}", e),
+                    poem::http::StatusCode::BAD_REQUEST,
+                )
+            })?
+            .validate()
+    }
+}
+
+/// A setting stored before settings were typed, these can no longer be submitted
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventLegacySettingChanged {
     pub setting: String,
     pub value: serde_json::Value,
 }
 
+/// Emitted when the owner rotates the party secret, the new secret itself is not part of the event
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventSecretRotated {}
+
+/// Emitted when the owner promotes or demotes a member
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventRoleChanged {
+    pub user_id: String,
+    pub role: PartyRole,
+}
+
+/// Emitted when an owner or moderator removes a member from the party
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventMemberKicked {
+    pub user_id: String,
+    /// Banned users can not join the party again
+    pub ban: bool,
+}
+
+/// Emitted when a member opens the lock, no codes can be submitted after this one
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventCodeFound {
+    pub user_id: String,
+    pub code: String,
+    /// Position of the code among the distinct codes the party tried on the lock, starting at 1
+    pub attempt: u32,
+    #[serde(default = "default_lock_id")]
+    #[oai(default = "default_lock_id")]
+    pub lock_id: String,
+}
+
+/// Emitted when another code lock is added to the raid
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventLockCreated {
+    pub lock_id: String,
+    pub name: String,
+}
+
+/// Emitted when the party is archived, no events can be submitted after this one
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyEventArchived {
+    /// Archived by the server because the party went idle or its last member left
+    pub automatic: bool,
+}
+
 impl PartyEvent {
     pub async fn create(
         party_id: &str,
@@ -124,55 +443,373 @@ impl PartyEvent {
         data: PartyEventData,
         state: &AppState,
     ) -> Result<Self, sqlx::Error> {
-        // Serialize the data to a JSON string for storage in the database
-        let data_json =
-            serde_json::to_string(&data).expect("Failed to serialize PartyEventData to JSON");
+        let mut tx = state.database.pool.begin().await?;
+
+        let event = PartyEvent::create_in_tx(&mut tx, party_id, user_id, data).await?;
+
+        tx.commit().await?;
+
+        // Push the event to any live subscribers of this party
+        state.party_hub.publish(&event);
+
+        Ok(event)
+    }
+
+    /// Insert an event as part of a larger transaction
+    /// The caller is responsible for publishing the event to the party hub once the transaction is committed
+    pub async fn create_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        user_id: &str,
+        data: PartyEventData,
+    ) -> Result<Self, sqlx::Error> {
+        PartyEvent::lock_party_in_tx(tx, party_id).await?;
+
+        PartyEvent::insert_in_tx(tx, party_id, user_id, data, None).await
+    }
+
+    /// Insert an event under an idempotency key as part of a larger transaction
+    ///
+    /// The caller looks the key up with `get_by_idempotency_key_in_tx` first,
+    /// both under the party lock so a retry can not slip in between.
+    pub async fn create_idempotent_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        user_id: &str,
+        data: PartyEventData,
+        idempotency_key: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        PartyEvent::lock_party_in_tx(tx, party_id).await?;
+
+        PartyEvent::insert_in_tx(tx, party_id, user_id, data, idempotency_key).await
+    }
+
+    pub async fn get_by_idempotency_key_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let event = sqlx::query_as!(
+            PartyEventDb,
+            "SELECT * FROM events WHERE party_id = $1 AND idempotency_key = $2",
+            party_id,
+            idempotency_key
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        event.map(PartyEvent::try_from).transpose()
+    }
+
+    /// Take a per-party lock for the rest of the transaction so event ids of a party
+    /// become visible in order, the party state projection relies on this
+    /// Taking it again later in the same transaction is a no-op
+    pub async fn lock_party_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!("SELECT pg_advisory_xact_lock(hashtext($1))", party_id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn insert_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        user_id: &str,
+        data: PartyEventData,
+        idempotency_key: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        // Wrap the data in a versioned envelope for storage in the database
+        let data_json = serde_json::to_value(PartyEventEnvelope {
+            version: PARTY_EVENT_VERSION,
+            data: serde_json::to_value(&data).expect("Failed to serialize PartyEventData to JSON"),
+        })
+        .expect("Failed to serialize PartyEventEnvelope to JSON");
 
         let db_event = sqlx::query_as!(
             PartyEventDb,
-            "INSERT INTO events (party_id, user_id, data) VALUES ($1, $2, $3) RETURNING *",
+            "INSERT INTO events (party_id, user_id, data, idempotency_key) VALUES ($1, $2, $3, $4) RETURNING *",
             party_id,
             user_id,
             data_json,
+            idempotency_key,
         )
-        .fetch_one(&state.database.pool)
+        .fetch_one(&mut **tx)
         .await?;
 
         // Convert from DB model to domain model
-        Ok(db_event.into())
+        let event = PartyEvent::try_from(db_event)?;
+
+        // Columns that mirror the event log are written together with the event that changes them
+        match &event.data {
+            PartyEventData::PartySettingChanged(setting) => {
+                if let Ok(setting) = PartySetting::try_from(setting) {
+                    Party::update_listing_in_tx(tx, party_id, &setting).await?;
+                }
+            }
+            PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                user_id, is_join, ..
+            }) => {
+                Party::update_membership_in_tx(tx, party_id, user_id, *is_join).await?;
+            }
+            PartyEventData::PartyMemberKicked(PartyEventMemberKicked { user_id, .. }) => {
+                Party::update_membership_in_tx(tx, party_id, user_id, false).await?;
+            }
+            PartyEventData::PartyArchived(_) => {
+                sqlx::query!(
+                    "UPDATE parties SET archived_at = COALESCE(archived_at, $2) WHERE party_id = $1",
+                    party_id,
+                    event.created_at
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            _ => {}
+        }
+
+        Ok(event)
+    }
+
+    /// A page of at most `limit` events matching the filter
+    /// Fetches one extra row to tell whether there are more events past the page
+    pub async fn get_events_page(
+        party_id: &str,
+        filter: &PartyEventFilter,
+        limit: u32,
+        state: &AppState,
+    ) -> Result<(Vec<Self>, bool), sqlx::Error> {
+        let types: Vec<String> = filter
+            .types
+            .iter()
+            .map(|event_type| event_type.storage_key().to_string())
+            .collect();
+
+        // Rows from before the versioned envelope keep their data at the top level
+        let mut events = match filter.before {
+            Some(before) => {
+                sqlx::query_as!(
+                    PartyEventDb,
+                    "SELECT * FROM events WHERE party_id = $1 AND event_id < $2 AND ($3::TEXT IS NULL OR user_id = $3) AND (cardinality($4::TEXT[]) = 0 OR COALESCE(data->'data', data) ?| $4) ORDER BY event_id DESC LIMIT $5",
+                    party_id,
+                    before,
+                    filter.user_id,
+                    &types,
+                    limit as i64 + 1
+                )
+                .fetch_all(&state.database.pool)
+                .await?
+            }
+            None => {
+                sqlx::query_as!(
+                    PartyEventDb,
+                    "SELECT * FROM events WHERE party_id = $1 AND event_id > $2 AND ($3::TEXT IS NULL OR user_id = $3) AND (cardinality($4::TEXT[]) = 0 OR COALESCE(data->'data', data) ?| $4) ORDER BY event_id ASC LIMIT $5",
+                    party_id,
+                    filter.after.unwrap_or(0),
+                    filter.user_id,
+                    &types,
+                    limit as i64 + 1
+                )
+                .fetch_all(&state.database.pool)
+                .await?
+            }
+        };
+
+        let has_more = events.len() > limit as usize;
+        events.truncate(limit as usize);
+
+        let events = events
+            .into_iter()
+            .map(PartyEvent::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok((events, has_more))
     }
 
-    pub async fn get_events_by_event_cursor(
+    /// Every event after `event_cursor`, in the order they were stored
+    /// Rows that can not be decoded are skipped so a single bad event does not break the projection
+    pub async fn get_events_after(
         party_id: &str,
         event_cursor: i32,
         state: &AppState,
     ) -> Result<Vec<Self>, sqlx::Error> {
-        let events = sqlx::query_as!(
-            PartyEventDb,
-            "SELECT * FROM events WHERE party_id = $1 AND event_id > $2 ORDER BY event_id ASC LIMIT 20",
-            party_id,
-            event_cursor
-        )
-        .fetch_all(&state.database.pool)
-        .await?;
+        PartyEvent::fetch_events_after(&state.database.pool, party_id, event_cursor).await
+    }
 
-        Ok(events.into_iter().map(|e| e.into()).collect())
+    /// `get_events_after` on the connection of a transaction, for callers holding the party lock
+    pub async fn get_events_after_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        event_cursor: i32,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        PartyEvent::fetch_events_after(&mut **tx, party_id, event_cursor).await
     }
 
-    pub async fn get_join_leave_events(
+    async fn fetch_events_after(
+        executor: impl PgExecutor<'_>,
         party_id: &str,
-        state: &AppState,
+        event_cursor: i32,
     ) -> Result<Vec<Self>, sqlx::Error> {
         let events = sqlx::query_as!(
             PartyEventDb,
-            "SELECT * FROM events WHERE party_id = $1",
-            party_id
+            "SELECT * FROM events WHERE party_id = $1 AND event_id > $2 ORDER BY event_id ASC",
+            party_id,
+            event_cursor
         )
-        .fetch_all(&state.database.pool)
+        .fetch_all(executor)
         .await?;
 
-        let events: Vec<PartyEvent> = events.into_iter().map(|e| e.into()).collect();
+        Ok(events
+            .into_iter()
+            .filter_map(|event| {
+                let event_id = event.event_id;
+
+                match PartyEvent::try_from(event) {
+                    Ok(event) => Some(event),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Skipping undecodable event {} of party {}: {}",
+                            event_id,
+                            party_id,
+                            e
+                        );
+                        None
+                    }
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const AUTHOR: &str = "steam:76561197960435530";
+
+    fn decode(data: serde_json::Value) -> PartyEvent {
+        PartyEvent::try_from(PartyEventDb {
+            party_id: "party".to_string(),
+            event_id: 1,
+            user_id: AUTHOR.to_string(),
+            data,
+            created_at: Utc::now(),
+            idempotency_key: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn upcast_v0_fills_deprecated_user_ids() {
+        for submitted in [
+            json!({ "user_id": "deprecated", "codes": ["1234"] }),
+            json!({ "codes": ["1234"] }),
+        ] {
+            let data = upcast(0, json!({ "user_codes_submitted": submitted }), AUTHOR);
+
+            assert_eq!(data["user_codes_submitted"]["user_id"], AUTHOR);
+        }
+
+        let data = upcast(
+            0,
+            json!({ "user_cursor_update": { "user_id": "deprecated", "cursor": "0", "size": 5 } }),
+            AUTHOR,
+        );
+        assert_eq!(data["user_cursor_update"]["user_id"], AUTHOR);
+
+        let data = upcast(
+            0,
+            json!({ "user_codes_submitted": { "user_id": "steam:1", "codes": [] } }),
+            AUTHOR,
+        );
+        assert_eq!(data["user_codes_submitted"]["user_id"], "steam:1");
+    }
+
+    #[test]
+    fn upcast_v0_defaults_list_order_reverse() {
+        let data = upcast(
+            0,
+            json!({ "party_list_order_changed": { "order": [{ "name": "a" }, { "name": "b", "reverse": true }] } }),
+            AUTHOR,
+        );
+
+        assert_eq!(
+            data["party_list_order_changed"]["order"],
+            json!([{ "name": "a", "reverse": false }, { "name": "b", "reverse": true }])
+        );
+    }
+
+    #[test]
+    fn upcast_v1_moves_unknown_settings() {
+        let data = upcast(
+            1,
+            json!({ "party_setting_changed": { "setting": "theme", "value": "dark" } }),
+            AUTHOR,
+        );
+        assert_eq!(
+            data,
+            json!({ "party_legacy_setting_changed": { "setting": "theme", "value": "dark" } })
+        );
+
+        let known = json!({ "party_setting_changed": { "setting": "private", "value": true } });
+        assert_eq!(upcast(1, known.clone(), AUTHOR), known);
+    }
+
+    #[test]
+    fn upcast_leaves_current_version_alone() {
+        let data =
+            json!({ "user_codes_submitted": { "user_id": "deprecated", "codes": ["1234"] } });
+
+        assert_eq!(upcast(PARTY_EVENT_VERSION, data.clone(), AUTHOR), data);
+    }
+
+    #[test]
+    fn decodes_bare_and_enveloped_rows() {
+        let bare = decode(
+            json!({ "user_codes_submitted": { "user_id": "deprecated", "codes": ["1234"] } }),
+        );
+        let PartyEventData::PartyCodesSubmitted(submitted) = bare.data else {
+            panic!("expected PartyCodesSubmitted, got {:?}", bare.data);
+        };
+        assert_eq!(submitted.user_id, AUTHOR);
+        assert_eq!(submitted.lock_id, default_lock_id());
+
+        let legacy = decode(json!({
+            "version": 1,
+            "data": { "party_setting_changed": { "setting": "theme", "value": "dark" } },
+        }));
+        assert!(matches!(
+            legacy.data,
+            PartyEventData::PartyLegacySettingChanged(PartyEventLegacySettingChanged { ref setting, .. }) if setting == "theme"
+        ));
+
+        let current = decode(json!({
+            "version": PARTY_EVENT_VERSION,
+            "data": { "user_chat_message": { "message": "hi" } },
+        }));
+        assert!(matches!(
+            current.data,
+            PartyEventData::PartyChatMessage(PartyEventChatMessage { ref message }) if message == "hi"
+        ));
+    }
+
+    #[test]
+    fn storage_keys_match_serialization() {
+        let data = PartyEventData::PartyChatMessage(PartyEventChatMessage {
+            message: "hi".to_string(),
+        });
 
-        Ok(events)
+        assert_eq!(
+            serde_json::to_value(&data).unwrap(),
+            json!({ PartyEventType::PartyChatMessage.storage_key(): { "message": "hi" } })
+        );
+        assert_eq!(
+            serde_json::to_value(PartyEventType::PartyJoinLeave).unwrap(),
+            PartyEventType::PartyJoinLeave.storage_key()
+        );
     }
 }
@@ -0,0 +1,319 @@
+use chrono::{DateTime, Utc};
+use futures::{future, stream, Stream, StreamExt};
+use poem_openapi::{types::ToJSON, Enum, Object};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    event::{
+        PartyEvent, PartyEventCodesSubmitted, PartyEventData, PartyEventFilter, PartyEventType,
+    },
+    Party,
+};
+use crate::state::AppState;
+
+/// Number of events read from the database at a time while exporting
+const EXPORT_PAGE_SIZE: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "lowercase")]
+#[oai(rename_all = "lowercase")]
+pub enum PartyExportFormat {
+    Csv,
+    /// A single json array
+    Json,
+    /// One json object per line
+    Ndjson,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum PartyExportKind {
+    /// Every event of the party
+    #[default]
+    Events,
+    /// One row per code submitted, see `PartyCodeAttempt`
+    Attempts,
+}
+
+/// A single code tried on a lock
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyCodeAttempt {
+    /// Event the code was submitted in
+    pub event_id: i32,
+    pub user_id: String,
+    pub lock_id: String,
+    pub code: String,
+    pub tried_at: DateTime<Utc>,
+}
+
+enum PartyExportRow {
+    Event(PartyEvent),
+    Attempt(PartyCodeAttempt),
+}
+
+impl PartyExportRow {
+    fn csv_header(kind: PartyExportKind) -> &'static str {
+        match kind {
+            PartyExportKind::Events => "event_id,created_at,user_id,type,data\n",
+            PartyExportKind::Attempts => "event_id,tried_at,user_id,lock_id,code\n",
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let fields = match self {
+            PartyExportRow::Event(event) => {
+                let data = event.data.to_json();
+                let event_type = data
+                    .as_ref()
+                    .and_then(|data| data.get("type"))
+                    .and_then(|event_type| event_type.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+
+                [
+                    event.event_id.to_string(),
+                    event.created_at.to_rfc3339(),
+                    event.user_id.clone(),
+                    event_type,
+                    event.data.to_json_string(),
+                ]
+            }
+            PartyExportRow::Attempt(attempt) => [
+                attempt.event_id.to_string(),
+                attempt.tried_at.to_rfc3339(),
+                attempt.user_id.clone(),
+                attempt.lock_id.clone(),
+                attempt.code.clone(),
+            ],
+        };
+
+        let mut line = fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        line.push('\n');
+
+        line
+    }
+
+    fn to_json_string(&self) -> String {
+        match self {
+            PartyExportRow::Event(event) => event.to_json_string(),
+            PartyExportRow::Attempt(attempt) => attempt.to_json_string(),
+        }
+    }
+}
+
+/// Quote a csv field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn export_rows(events: Vec<PartyEvent>, kind: PartyExportKind) -> Vec<PartyExportRow> {
+    match kind {
+        PartyExportKind::Events => events.into_iter().map(PartyExportRow::Event).collect(),
+        PartyExportKind::Attempts => events
+            .into_iter()
+            .flat_map(|event| {
+                let PartyEventData::PartyCodesSubmitted(PartyEventCodesSubmitted {
+                    codes,
+                    lock_id,
+                    ..
+                }) = event.data
+                else {
+                    return Vec::new();
+                };
+
+                codes
+                    .into_iter()
+                    .map(|code| {
+                        PartyExportRow::Attempt(PartyCodeAttempt {
+                            event_id: event.event_id,
+                            user_id: event.user_id.clone(),
+                            lock_id: lock_id.clone(),
+                            code,
+                            tried_at: event.created_at,
+                        })
+                    })
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+impl Party {
+    /// The party's events, or its code attempts, encoded as `format` oldest first
+    /// Events are read a page at a time as the stream is polled, so the export is never held in memory
+    pub fn export(
+        party_id: String,
+        format: PartyExportFormat,
+        kind: PartyExportKind,
+        state: AppState,
+    ) -> impl Stream<Item = Result<String, std::io::Error>> + Send + 'static {
+        let types = match kind {
+            PartyExportKind::Events => vec![],
+            PartyExportKind::Attempts => vec![PartyEventType::PartyCodesSubmitted],
+        };
+
+        let pages = stream::unfold(Some(0), move |cursor| {
+            let state = state.clone();
+            let party_id = party_id.clone();
+            let types = types.clone();
+
+            async move {
+                let cursor = cursor?;
+
+                let filter = PartyEventFilter {
+                    after: Some(cursor),
+                    types,
+                    ..Default::default()
+                };
+
+                match PartyEvent::get_events_page(&party_id, &filter, EXPORT_PAGE_SIZE, &state)
+                    .await
+                {
+                    Ok((events, has_more)) => {
+                        let next = events
+                            .last()
+                            .map(|event| event.event_id)
+                            .filter(|_| has_more);
+                        Some((Ok(events), next))
+                    }
+                    Err(e) => {
+                        tracing::error!("Error exporting events: {:?}", e);
+                        Some((Err(std::io::Error::other(e)), None))
+                    }
+                }
+            }
+        });
+
+        let mut first = true;
+        let body = pages.map(move |page| {
+            let mut chunk = String::new();
+
+            for row in export_rows(page?, kind) {
+                match format {
+                    PartyExportFormat::Csv => chunk.push_str(&row.to_csv()),
+                    PartyExportFormat::Json => {
+                        if !first {
+                            chunk.push(',');
+                        }
+                        chunk.push_str(&row.to_json_string());
+                    }
+                    PartyExportFormat::Ndjson => {
+                        chunk.push_str(&row.to_json_string());
+                        chunk.push('\n');
+                    }
+                }
+
+                first = false;
+            }
+
+            Ok(chunk)
+        });
+
+        let (header, footer) = match format {
+            PartyExportFormat::Csv => (PartyExportRow::csv_header(kind), ""),
+            PartyExportFormat::Json => ("[", "]\n"),
+            PartyExportFormat::Ndjson => ("", ""),
+        };
+
+        stream::once(future::ready(Ok(header.to_string())))
+            .chain(body)
+            .chain(stream::once(future::ready(Ok(footer.to_string()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::party::event::PartyEventChatMessage;
+
+    fn event(event_id: i32, data: PartyEventData) -> PartyEvent {
+        PartyEvent {
+            party_id: "party".to_string(),
+            event_id,
+            user_id: "steam:1".to_string(),
+            data,
+            created_at: Utc::now(),
+            idempotency_key: None,
+        }
+    }
+
+    fn events() -> Vec<PartyEvent> {
+        vec![
+            event(
+                1,
+                PartyEventData::PartyChatMessage(PartyEventChatMessage {
+                    message: "hi, all".to_string(),
+                }),
+            ),
+            event(
+                2,
+                PartyEventData::PartyCodesSubmitted(PartyEventCodesSubmitted {
+                    user_id: "steam:1".to_string(),
+                    codes: vec!["1234".to_string(), "0000".to_string()],
+                    lock_id: "garage".to_string(),
+                }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn csv_field_quotes_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("two\rlines"), "\"two\rlines\"");
+    }
+
+    #[test]
+    fn export_rows_of_events() {
+        let rows = export_rows(events(), PartyExportKind::Events);
+
+        assert_eq!(rows.len(), 2);
+        assert!(matches!(&rows[0], PartyExportRow::Event(event) if event.event_id == 1));
+        // the data column holds quotes and a comma, so it is quoted with its quotes doubled
+        // the chat message holds a comma, so the data column is quoted
+        let line = rows[0].to_csv();
+        assert!(line.starts_with("1,"));
+        assert!(line.contains(",PartyChatMessage,\"{\"\"message\"\":\"\"hi, all\"\""));
+        assert!(line.ends_with("\"\n"));
+    }
+
+    #[test]
+    fn export_rows_of_attempts() {
+        let rows = export_rows(events(), PartyExportKind::Attempts);
+
+        let attempts: Vec<_> = rows
+            .iter()
+            .map(|row| match row {
+                PartyExportRow::Attempt(attempt) => attempt,
+                PartyExportRow::Event(_) => panic!("expected only attempts"),
+            })
+            .collect();
+
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0].code, "1234");
+        assert_eq!(attempts[1].code, "0000");
+        assert!(attempts
+            .iter()
+            .all(|attempt| attempt.event_id == 2 && attempt.lock_id == "garage"));
+
+        let line = rows[0].to_csv();
+        assert_eq!(
+            line.split(',').count(),
+            PartyExportRow::csv_header(PartyExportKind::Attempts)
+                .split(',')
+                .count()
+        );
+        assert!(line.ends_with(",steam:1,garage,1234\n"));
+    }
+}
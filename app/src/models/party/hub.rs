@@ -0,0 +1,199 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use futures::{
+    channel::mpsc::{channel, Receiver, Sender},
+    stream::{self, BoxStream},
+    StreamExt,
+};
+
+use super::event::PartyEvent;
+
+/// In-process fan-out of freshly committed party events
+///
+/// Every open event stream registers a sender for its party, when an event
+/// is created it is pushed to all subscribers of that party.
+/// Events are published after their transaction commits, so they may arrive out of order.
+/// A subscriber whose buffer is full is dropped, ending its stream so the
+/// client reconnects and catches up from its cursor.
+/// Closed receivers are pruned on the next subscribe or publish.
+pub struct PartyHub {
+    subscribers: Mutex<HashMap<String, Vec<Sender<PartyEvent>>>>,
+}
+
+/// Events buffered for a subscriber before it is considered too slow
+const SUBSCRIBER_BUFFER: usize = 256;
+
+impl PartyHub {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self, party_id: &str) -> Receiver<PartyEvent> {
+        let (sender, receiver) = channel(SUBSCRIBER_BUFFER);
+
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let senders = subscribers.entry(party_id.to_string()).or_default();
+        senders.retain(|sender| !sender.is_closed());
+        senders.push(sender);
+
+        receiver
+    }
+
+    /// Subscribe on behalf of a member
+    /// The stream ends after the event that removes the member from the party, so kicked
+    /// and banned users stop receiving events and their sender is pruned
+    pub fn subscribe_member(
+        &self,
+        party_id: &str,
+        user_id: &str,
+    ) -> BoxStream<'static, PartyEvent> {
+        let user_id = user_id.to_string();
+        let receiver = self.subscribe(party_id);
+
+        stream::unfold(Some(receiver), move |receiver| {
+            let user_id = user_id.clone();
+
+            async move {
+                let mut receiver = receiver?;
+                let event = receiver.next().await?;
+
+                // end the stream right after the member's own removal is delivered
+                let receiver = (!event.data.removes_member(&user_id)).then_some(receiver);
+
+                Some((event, receiver))
+            }
+        })
+        .boxed()
+    }
+
+    /// Drop every subscriber of the party, ending their streams
+    pub fn close(&self, party_id: &str) {
+        self.subscribers.lock().unwrap().remove(party_id);
+    }
+
+    pub fn publish(&self, event: &PartyEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+
+        if let Some(senders) = subscribers.get_mut(&event.party_id) {
+            senders.retain_mut(|sender| sender.try_send(event.clone()).is_ok());
+
+            if senders.is_empty() {
+                subscribers.remove(&event.party_id);
+            }
+        }
+    }
+}
+
+impl Default for PartyHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use futures::{FutureExt, Stream};
+
+    use super::*;
+    use crate::models::party::event::{PartyEventData, PartyEventJoinLeave};
+
+    fn join_leave(party_id: &str, event_id: i32, user_id: &str, is_join: bool) -> PartyEvent {
+        PartyEvent {
+            party_id: party_id.to_string(),
+            event_id,
+            user_id: user_id.to_string(),
+            data: PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                user_id: user_id.to_string(),
+                is_join,
+                invite_id: None,
+            }),
+            created_at: Utc::now(),
+            idempotency_key: None,
+        }
+    }
+
+    fn next_event_id<S: Stream<Item = PartyEvent> + Unpin>(stream: &mut S) -> Option<i32> {
+        stream
+            .next()
+            .now_or_never()
+            .flatten()
+            .map(|event| event.event_id)
+    }
+
+    #[test]
+    fn publish_fans_out_to_the_party() {
+        let hub = PartyHub::new();
+        let mut first = hub.subscribe("a");
+        let mut second = hub.subscribe("a");
+        let mut other = hub.subscribe("b");
+
+        hub.publish(&join_leave("a", 1, "steam:1", true));
+
+        assert_eq!(next_event_id(&mut first), Some(1));
+        assert_eq!(next_event_id(&mut second), Some(1));
+        assert!(other.next().now_or_never().is_none());
+    }
+
+    #[test]
+    fn publish_prunes_closed_and_full_subscribers() {
+        let hub = PartyHub::new();
+        let closed = hub.subscribe("a");
+        let mut full = hub.subscribe("a");
+        drop(closed);
+
+        for event_id in 0..=SUBSCRIBER_BUFFER as i32 {
+            hub.publish(&join_leave("a", event_id, "steam:1", true));
+        }
+        assert_eq!(hub.subscribers.lock().unwrap()["a"].len(), 1);
+
+        // the full subscriber is dropped and its stream ends after the buffered events
+        hub.publish(&join_leave("a", 1000, "steam:1", true));
+        assert!(!hub.subscribers.lock().unwrap().contains_key("a"));
+
+        let mut received = 0;
+        while next_event_id(&mut full).is_some() {
+            received += 1;
+        }
+        assert_eq!(received, SUBSCRIBER_BUFFER + 1);
+        assert!(full
+            .next()
+            .now_or_never()
+            .is_some_and(|event| event.is_none()));
+    }
+
+    #[test]
+    fn close_ends_the_party_streams() {
+        let hub = PartyHub::new();
+        let mut closed = hub.subscribe("a");
+        let mut other = hub.subscribe("b");
+
+        hub.close("a");
+        hub.publish(&join_leave("b", 1, "steam:1", true));
+
+        assert!(closed
+            .next()
+            .now_or_never()
+            .is_some_and(|event| event.is_none()));
+        assert_eq!(next_event_id(&mut other), Some(1));
+    }
+
+    #[test]
+    fn member_stream_ends_after_removal() {
+        let hub = PartyHub::new();
+        let mut member = hub.subscribe_member("a", "steam:2");
+
+        hub.publish(&join_leave("a", 1, "steam:3", false));
+        hub.publish(&join_leave("a", 2, "steam:2", false));
+        hub.publish(&join_leave("a", 3, "steam:1", true));
+
+        assert_eq!(next_event_id(&mut member), Some(1));
+        assert_eq!(next_event_id(&mut member), Some(2));
+        assert!(member
+            .next()
+            .now_or_never()
+            .is_some_and(|event| event.is_none()));
+    }
+}
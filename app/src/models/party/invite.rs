@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqids::Sqids;
+use sqlx::{Postgres, Transaction};
+
+use crate::state::AppState;
+
+/// An invite token for a party, optionally limited in time and number of uses
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyInvite {
+    pub invite_id: String,
+    pub party_id: String,
+    pub created_by: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub revoked: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PartyInvite {
+    pub async fn create(
+        party_id: &str,
+        created_by: &str,
+        expires_at: Option<DateTime<Utc>>,
+        max_uses: Option<i32>,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        let random_number = rand::rng().random_range(0..u64::MAX);
+        let invite_id = Sqids::default().encode(&[1, random_number]).unwrap();
+
+        let invite = sqlx::query_as!(
+            Self,
+            "INSERT INTO party_invites (invite_id, party_id, created_by, expires_at, max_uses) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+            invite_id,
+            party_id,
+            created_by,
+            expires_at,
+            max_uses
+        )
+        .fetch_one(&state.database.pool)
+        .await?;
+
+        Ok(invite)
+    }
+
+    pub async fn get_by_party(party_id: &str, state: &AppState) -> Result<Vec<Self>, sqlx::Error> {
+        let invites = sqlx::query_as!(
+            Self,
+            "SELECT * FROM party_invites WHERE party_id = $1 ORDER BY created_at DESC",
+            party_id
+        )
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok(invites)
+    }
+
+    pub async fn revoke(
+        party_id: &str,
+        invite_id: &str,
+        state: &AppState,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let invite = sqlx::query_as!(
+            Self,
+            "UPDATE party_invites SET revoked = TRUE WHERE party_id = $1 AND invite_id = $2 RETURNING *",
+            party_id,
+            invite_id
+        )
+        .fetch_optional(&state.database.pool)
+        .await?;
+
+        Ok(invite)
+    }
+
+    /// Count a use of the invite, returns `None` if the invite is unknown, revoked, expired or used up
+    pub async fn redeem_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        invite_id: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let invite = sqlx::query_as!(
+            Self,
+            "UPDATE party_invites SET uses = uses + 1 WHERE party_id = $1 AND invite_id = $2 AND NOT revoked AND (expires_at IS NULL OR expires_at > NOW()) AND (max_uses IS NULL OR uses < max_uses) RETURNING *",
+            party_id,
+            invite_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(invite)
+    }
+}
@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bitmap::CodeBitmap,
+    event::{PartyEvent, PartyEventCodesAssigned, PartyEventData},
+    lock::default_lock_id,
+    Party, PartyState,
+};
+use crate::{models::codes::load_code_sequence_in_tx, state::AppState};
+
+/// How long a user gets to try their assigned codes before they go back into the pool
+pub const LEASE_DURATION_SECS: i64 = 5 * 60;
+
+/// Largest batch a user can be assigned at once
+pub const MAX_LEASE_SIZE: u32 = 100;
+
+/// A batch of codes handed to a single user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PartyCodeLease {
+    pub codes: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default = "default_lock_id")]
+    pub lock_id: String,
+}
+
+impl PartyCodeLease {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at > now
+    }
+}
+
+impl Party {
+    /// Lease the next `size` codes not yet tried on the lock that are not leased to anyone else
+    ///
+    /// Any previous lease of the user is released, as are leases that expired.
+    /// Assignments hold the party lock so two concurrent callers never receive the same codes,
+    /// the next caller reads this lease through `get_party_state_in_tx`.
+    pub async fn assign_codes(
+        party_id: &str,
+        user_id: &str,
+        lock_id: &str,
+        size: u32,
+        state: &AppState,
+    ) -> Result<PartyEvent, Arc<sqlx::Error>> {
+        let mut tx = state.database.pool.begin().await.map_err(Arc::new)?;
+
+        PartyEvent::lock_party_in_tx(&mut tx, party_id)
+            .await
+            .map_err(Arc::new)?;
+
+        let party_state = Party::get_party_state_in_tx(&mut tx, party_id, state).await?;
+
+        let sequence = load_code_sequence_in_tx(&mut tx, &party_state.list_order, state)
+            .await
+            .map_err(Arc::new)?;
+
+        let now = Utc::now();
+        let codes = party_state.next_lease_codes(sequence, user_id, lock_id, size, now);
+
+        let event = PartyEvent::create_in_tx(
+            &mut tx,
+            party_id,
+            user_id,
+            PartyEventData::PartyCodesAssigned(PartyEventCodesAssigned {
+                user_id: user_id.to_string(),
+                codes,
+                expires_at: now + Duration::seconds(LEASE_DURATION_SECS),
+                lock_id: lock_id.to_string(),
+            }),
+        )
+        .await
+        .map_err(Arc::new)?;
+
+        tx.commit().await.map_err(Arc::new)?;
+
+        state.party_hub.publish(&event);
+
+        Ok(event)
+    }
+}
+
+impl PartyState {
+    /// The first `size` codes of `sequence` that were not tried on the lock
+    /// and are not leased to anyone but `user_id`
+    pub fn next_lease_codes(
+        &self,
+        sequence: Vec<String>,
+        user_id: &str,
+        lock_id: &str,
+        size: u32,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        // codes that were tried on the lock or are leased to someone else
+        let mut taken: CodeBitmap = self
+            .leases
+            .iter()
+            .filter(|(lease_user_id, lease)| {
+                *lease_user_id != user_id && lease.lock_id == lock_id && lease.is_active(now)
+            })
+            .flat_map(|(_, lease)| lease.codes.iter())
+            .collect();
+
+        if let Some(lock) = self.locks.get(lock_id) {
+            taken |= &lock.tried_codes;
+        }
+
+        sequence
+            .into_iter()
+            .filter(|code| !taken.contains(code))
+            .take(size.min(MAX_LEASE_SIZE) as usize)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::party::lock::DEFAULT_LOCK_ID;
+
+    const RAIDER: &str = "steam:2";
+    const OTHER: &str = "steam:3";
+
+    fn sequence() -> Vec<String> {
+        ["1111", "2222", "3333", "4444", "5555"]
+            .iter()
+            .map(|code| code.to_string())
+            .collect()
+    }
+
+    fn lease(codes: &[&str], lock_id: &str, expires_at: DateTime<Utc>) -> PartyCodeLease {
+        PartyCodeLease {
+            codes: codes.iter().map(|code| code.to_string()).collect(),
+            expires_at,
+            lock_id: lock_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn lease_skips_tried_codes() {
+        let mut state = PartyState::new(None);
+        state
+            .locks
+            .get_mut(DEFAULT_LOCK_ID)
+            .unwrap()
+            .try_codes(["1111", "3333"]);
+
+        let codes = state.next_lease_codes(sequence(), RAIDER, DEFAULT_LOCK_ID, 2, Utc::now());
+        assert_eq!(codes, ["2222", "4444"]);
+    }
+
+    #[test]
+    fn lease_skips_active_leases_of_others_on_the_lock() {
+        let now = Utc::now();
+        let mut state = PartyState::new(None);
+        state.leases.insert(
+            OTHER.to_string(),
+            lease(
+                &["1111", "2222"],
+                DEFAULT_LOCK_ID,
+                now + Duration::minutes(1),
+            ),
+        );
+
+        let codes = state.next_lease_codes(sequence(), RAIDER, DEFAULT_LOCK_ID, 2, now);
+        assert_eq!(codes, ["3333", "4444"]);
+
+        // a user's own lease is handed out again
+        let codes = state.next_lease_codes(sequence(), OTHER, DEFAULT_LOCK_ID, 2, now);
+        assert_eq!(codes, ["1111", "2222"]);
+    }
+
+    #[test]
+    fn lease_reuses_expired_and_other_lock_leases() {
+        let now = Utc::now();
+        let mut state = PartyState::new(None);
+        state.leases.insert(
+            OTHER.to_string(),
+            lease(&["1111"], DEFAULT_LOCK_ID, now - Duration::minutes(1)),
+        );
+        state.leases.insert(
+            "steam:4".to_string(),
+            lease(&["2222"], "garage", now + Duration::minutes(1)),
+        );
+
+        let codes = state.next_lease_codes(sequence(), RAIDER, DEFAULT_LOCK_ID, 2, now);
+        assert_eq!(codes, ["1111", "2222"]);
+    }
+
+    #[test]
+    fn lease_size_is_capped() {
+        let sequence: Vec<String> = (0..1000).map(|code| format!("{:04}", code)).collect();
+
+        let codes = PartyState::new(None).next_lease_codes(
+            sequence,
+            RAIDER,
+            DEFAULT_LOCK_ID,
+            MAX_LEASE_SIZE + 50,
+            Utc::now(),
+        );
+        assert_eq!(codes.len(), MAX_LEASE_SIZE as usize);
+    }
+}
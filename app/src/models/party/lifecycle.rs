@@ -0,0 +1,236 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::{Postgres, Transaction};
+use tracing::{info, warn};
+
+use super::{
+    event::{PartyEvent, PartyEventArchived, PartyEventCodeFound, PartyEventData},
+    Party,
+};
+use crate::state::AppState;
+
+/// How often parties are checked for inactivity
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+impl Party {
+    /// Record the code that opened a lock and who found it
+    ///
+    /// The party counts as cracked once every lock is open.
+    /// Returns `None` if the lock is unknown or was already opened.
+    pub async fn mark_cracked(
+        party_id: &str,
+        user_id: &str,
+        lock_id: &str,
+        code: &str,
+        state: &AppState,
+    ) -> Result<Option<(Self, PartyEvent)>, Arc<sqlx::Error>> {
+        let mut tx = state.database.pool.begin().await.map_err(Arc::new)?;
+
+        // the found state is read under the party lock, so two members reporting
+        // the same lock at once can't both store a found code
+        PartyEvent::lock_party_in_tx(&mut tx, party_id)
+            .await
+            .map_err(Arc::new)?;
+
+        let party_state = Party::get_party_state_in_tx(&mut tx, party_id, state).await?;
+
+        let Some(lock) = party_state
+            .lock(lock_id)
+            .filter(|lock| lock.found.is_none())
+        else {
+            return Ok(None);
+        };
+
+        let attempt = lock.attempt_of(code);
+
+        // every other lock is open already, so this one closes out the raid
+        let cracks_party = party_state
+            .locks()
+            .iter()
+            .all(|(id, lock)| id == lock_id || lock.found.is_some());
+
+        let party = if cracks_party {
+            sqlx::query_as!(
+                Self,
+                "UPDATE parties SET cracked_at = COALESCE(cracked_at, NOW()), cracked_code = COALESCE(cracked_code, $2) WHERE party_id = $1 RETURNING *",
+                party_id,
+                code
+            )
+            .fetch_one(&mut *tx)
+            .await
+        } else {
+            sqlx::query_as!(Self, "SELECT * FROM parties WHERE party_id = $1", party_id)
+                .fetch_one(&mut *tx)
+                .await
+        }
+        .map_err(Arc::new)?;
+
+        let event = PartyEvent::create_in_tx(
+            &mut tx,
+            party_id,
+            user_id,
+            PartyEventData::PartyCodeFound(PartyEventCodeFound {
+                user_id: user_id.to_string(),
+                code: code.to_string(),
+                attempt,
+                lock_id: lock_id.to_string(),
+            }),
+        )
+        .await
+        .map_err(Arc::new)?;
+
+        tx.commit().await.map_err(Arc::new)?;
+
+        state.party_hub.publish(&event);
+
+        Ok(Some((party, event)))
+    }
+
+    /// Make the party read-only, its history stays available
+    ///
+    /// Returns `None` if the party was already archived.
+    pub async fn archive(
+        party_id: &str,
+        user_id: &str,
+        automatic: bool,
+        state: &AppState,
+    ) -> Result<Option<(Self, PartyEvent)>, sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        let archived = Party::archive_in_tx(&mut tx, party_id, user_id, automatic).await?;
+
+        tx.commit().await?;
+
+        if let Some((_, event)) = &archived {
+            state.party_hub.publish(event);
+        }
+
+        Ok(archived)
+    }
+
+    /// Archive the party as part of a larger transaction
+    /// The caller is responsible for publishing the event to the party hub once the transaction is committed
+    pub async fn archive_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        user_id: &str,
+        automatic: bool,
+    ) -> Result<Option<(Self, PartyEvent)>, sqlx::Error> {
+        // checked under the party lock so only one archive event is ever stored
+        PartyEvent::lock_party_in_tx(tx, party_id).await?;
+
+        let Some(mut party) = sqlx::query_as!(
+            Self,
+            "SELECT * FROM parties WHERE party_id = $1 AND archived_at IS NULL",
+            party_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        // storing the event sets `archived_at`
+        let event = PartyEvent::create_in_tx(
+            tx,
+            party_id,
+            user_id,
+            PartyEventData::PartyArchived(PartyEventArchived { automatic }),
+        )
+        .await?;
+
+        party.archived_at = Some(event.created_at);
+
+        Ok(Some((party, event)))
+    }
+
+    /// Permanently remove the party together with its events, snapshots and invites
+    pub async fn delete(party_id: &str, state: &AppState) -> Result<(), sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        // under the party lock no event can be stored between the deletes
+        PartyEvent::lock_party_in_tx(&mut tx, party_id).await?;
+
+        sqlx::query!("DELETE FROM events WHERE party_id = $1", party_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM party_snapshots WHERE party_id = $1", party_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM party_invites WHERE party_id = $1", party_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let list_ids = sqlx::query_scalar!(
+            "DELETE FROM code_lists WHERE party_id = $1 RETURNING list_id",
+            party_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM party_listings WHERE party_id = $1", party_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM party_members WHERE party_id = $1", party_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM parties WHERE party_id = $1", party_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        // end the event streams that are still open on the party
+        state.party_hub.close(party_id);
+
+        state.cache.party_state.invalidate(party_id).await;
+
+        for list_id in &list_ids {
+            state.cache.code_lists.invalidate(list_id).await;
+        }
+
+        Ok(())
+    }
+
+    /// Archive every party without new events for `archive_after_days`
+    pub async fn archive_idle(state: &AppState) -> Result<u32, Arc<sqlx::Error>> {
+        let idle = sqlx::query_as!(
+            Self,
+            "SELECT * FROM parties p WHERE archived_at IS NULL AND COALESCE((SELECT MAX(created_at) FROM events e WHERE e.party_id = p.party_id), p.created_at) < NOW() - make_interval(days => $1)",
+            state.party_config.archive_after_days as i32
+        )
+        .fetch_all(&state.database.pool)
+        .await
+        .map_err(Arc::new)?;
+
+        let mut archived = 0;
+
+        for party in idle {
+            if Party::archive(&party.party_id, &party.owner_id, true, state)
+                .await
+                .map_err(Arc::new)?
+                .is_some()
+            {
+                archived += 1;
+            }
+        }
+
+        Ok(archived)
+    }
+
+    pub async fn archive_idle_loop(state: &AppState) {
+        loop {
+            match Party::archive_idle(state).await {
+                Ok(0) => {}
+                Ok(archived) => info!(archived, "archived idle parties"),
+                Err(e) => warn!("Failed to archive idle parties: {}", e),
+            }
+
+            async_std::task::sleep(ARCHIVE_INTERVAL).await;
+        }
+    }
+}
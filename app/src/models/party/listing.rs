@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use super::{bitmap::CodeBitmap, role::PartyRole, settings::PartySetting, Party};
+use crate::{models::codes::load_code_sequence, state::AppState};
+
+/// A party and when anything last happened in it
+#[derive(Debug)]
+pub struct PartyActivity {
+    pub party_id: String,
+    pub last_activity: DateTime<Utc>,
+}
+
+/// Overview of a party as shown in party listings
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartySummary {
+    pub party_id: String,
+    pub owner_id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+    pub cracked_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub member_count: u32,
+    /// Role of the caller, `None` if they are not a member
+    pub role: Option<PartyRole>,
+    pub progress: PartyProgress,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyProgress {
+    /// Number of codes in the sequence that were tried so far, summed over all locks
+    pub tried: u32,
+    /// Number of codes in the party's sequence times the number of locks
+    pub total: u32,
+}
+
+impl Party {
+    /// Parties the user owns or is a member of, most recently active first
+    /// Reads the members mirrored into `party_members` when they join, leave or are kicked
+    pub async fn get_by_user(
+        user_id: &str,
+        offset: u32,
+        limit: u32,
+        state: &AppState,
+    ) -> Result<(i64, Vec<PartyActivity>), sqlx::Error> {
+        let total = sqlx::query_scalar!(
+            "SELECT COUNT(*) AS \"count!\" FROM parties p WHERE p.owner_id = $1 OR EXISTS (SELECT 1 FROM party_members m WHERE m.party_id = p.party_id AND m.user_id = $1)",
+            user_id
+        )
+        .fetch_one(&state.database.pool)
+        .await?;
+
+        let parties = sqlx::query_as!(
+            PartyActivity,
+            "SELECT p.party_id, MAX(e.created_at) AS \"last_activity!\" FROM parties p JOIN events e ON e.party_id = p.party_id WHERE p.owner_id = $1 OR EXISTS (SELECT 1 FROM party_members m WHERE m.party_id = p.party_id AND m.user_id = $1) GROUP BY p.party_id ORDER BY 2 DESC, p.party_id LIMIT $2 OFFSET $3",
+            user_id,
+            limit as i64,
+            offset as i64
+        )
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok((total, parties))
+    }
+
+    /// Public parties raiding on the given server that are not archived, most recently active first
+    /// Reads the discovery settings mirrored into `party_listings` when they are changed
+    pub async fn get_discoverable(
+        server_id: &str,
+        offset: u32,
+        limit: u32,
+        state: &AppState,
+    ) -> Result<(i64, Vec<PartyActivity>), sqlx::Error> {
+        let total = sqlx::query_scalar!(
+            "SELECT COUNT(*) AS \"count!\" FROM party_listings l JOIN parties p ON p.party_id = l.party_id WHERE l.server_id = $1 AND NOT l.private AND p.archived_at IS NULL",
+            server_id
+        )
+        .fetch_one(&state.database.pool)
+        .await?;
+
+        let parties = sqlx::query_as!(
+            PartyActivity,
+            "SELECT l.party_id, MAX(e.created_at) AS \"last_activity!\" FROM party_listings l JOIN parties p ON p.party_id = l.party_id JOIN events e ON e.party_id = l.party_id WHERE l.server_id = $1 AND NOT l.private AND p.archived_at IS NULL GROUP BY l.party_id ORDER BY 2 DESC, l.party_id LIMIT $2 OFFSET $3",
+            server_id,
+            limit as i64,
+            offset as i64
+        )
+        .fetch_all(&state.database.pool)
+        .await?;
+
+        Ok((total, parties))
+    }
+
+    /// Mirror a setting that discovery filters on into `party_listings`
+    /// Runs in the transaction that stores the setting event, other settings are ignored
+    pub async fn update_listing_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        setting: &PartySetting,
+    ) -> Result<(), sqlx::Error> {
+        match setting {
+            PartySetting::Private(private) => {
+                sqlx::query!(
+                    "INSERT INTO party_listings (party_id, private) VALUES ($1, $2) ON CONFLICT (party_id) DO UPDATE SET private = EXCLUDED.private",
+                    party_id,
+                    private
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            PartySetting::ServerId(server_id) => {
+                sqlx::query!(
+                    "INSERT INTO party_listings (party_id, server_id) VALUES ($1, $2) ON CONFLICT (party_id) DO UPDATE SET server_id = EXCLUDED.server_id",
+                    party_id,
+                    server_id.as_deref()
+                )
+                .execute(&mut **tx)
+                .await?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Mirror a membership change into `party_members`
+    /// Runs in the transaction that stores the join, leave or kick event
+    pub async fn update_membership_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        user_id: &str,
+        is_member: bool,
+    ) -> Result<(), sqlx::Error> {
+        if is_member {
+            sqlx::query!(
+                "INSERT INTO party_members (party_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                party_id,
+                user_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        } else {
+            sqlx::query!(
+                "DELETE FROM party_members WHERE party_id = $1 AND user_id = $2",
+                party_id,
+                user_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Summarize a party from its state as seen by `user_id`
+    /// `None` for parties the user is not in that are private or archived
+    pub async fn summary(
+        activity: PartyActivity,
+        user_id: &str,
+        state: &AppState,
+    ) -> Result<Option<PartySummary>, Arc<sqlx::Error>> {
+        let Some(party) = Party::get_by_id(&activity.party_id, state)
+            .await
+            .map_err(Arc::new)?
+        else {
+            return Ok(None);
+        };
+
+        let party_state = Party::get_party_state(&party.party_id, state).await?;
+
+        // outsiders only get to see parties that are listed publicly
+        if !party_state.is_owner_or_member(user_id)
+            && (party_state.settings().private || party.archived_at.is_some())
+        {
+            return Ok(None);
+        }
+
+        let sequence = load_code_sequence(&party_state.list_order, state)
+            .await
+            .map_err(Arc::new)?;
+        let sequence_codes: CodeBitmap = sequence.iter().collect();
+        let tried: u32 = party_state
+            .locks
+            .values()
+            .map(|lock| lock.tried_codes.intersection_count(&sequence_codes))
+            .sum();
+
+        Ok(Some(PartySummary {
+            party_id: party.party_id,
+            owner_id: party.owner_id,
+            created_at: party.created_at,
+            last_activity: activity.last_activity,
+            cracked_at: party.cracked_at,
+            archived_at: party.archived_at,
+            member_count: party_state.members.len() as u32,
+            role: party_state.role_of(user_id),
+            progress: PartyProgress {
+                tried,
+                total: (sequence.len() * party_state.locks.len()) as u32,
+            },
+        }))
+    }
+}
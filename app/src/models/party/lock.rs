@@ -0,0 +1,108 @@
+use poem_openapi::Object;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sqids::Sqids;
+
+use super::{
+    bitmap::CodeBitmap,
+    event::{PartyEvent, PartyEventData, PartyEventLockCreated},
+    summary::PartyFoundCode,
+    Party,
+};
+use crate::state::AppState;
+
+/// Every party starts with a single lock, events from before locks existed belong to it
+pub const DEFAULT_LOCK_ID: &str = "default";
+
+/// Name of the lock every party starts with
+pub const DEFAULT_LOCK_NAME: &str = "Main";
+
+pub fn default_lock_id() -> String {
+    DEFAULT_LOCK_ID.to_string()
+}
+
+/// Progress on a single code lock of the raid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyLockState {
+    pub name: String,
+    pub tried_codes: CodeBitmap,
+    /// Number of distinct codes tried on this lock, counted as submissions are applied
+    pub attempts: u32,
+    /// The code that opened this lock
+    pub found: Option<PartyFoundCode>,
+}
+
+impl PartyLockState {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            tried_codes: CodeBitmap::new(),
+            attempts: 0,
+            found: None,
+        }
+    }
+
+    /// Record submitted codes, only codes that were not tried before count as an attempt
+    pub fn try_codes<S: AsRef<str>>(&mut self, codes: impl IntoIterator<Item = S>) {
+        for code in codes {
+            if self.tried_codes.insert(code.as_ref()) {
+                self.attempts += 1;
+            }
+        }
+    }
+
+    /// Attempt that `code` opened the lock on, a code that was never submitted counts as the next one
+    pub fn attempt_of(&self, code: &str) -> u32 {
+        if self.tried_codes.contains(code) {
+            self.attempts
+        } else {
+            self.attempts + 1
+        }
+    }
+}
+
+/// Overview of a lock as returned by the api
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyLockProgress {
+    pub lock_id: String,
+    pub name: String,
+    /// Number of codes in the party's sequence that were tried on this lock
+    pub tried: u32,
+    /// Number of codes in the party's sequence
+    pub total: u32,
+    pub found: Option<PartyFoundCode>,
+}
+
+/// Every code tried on a lock, regardless of the party's sequence
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyLockTried {
+    pub lock_id: String,
+    /// Base64 of a bitmap over `0000` to `9999`, bit `n % 8` of byte `n / 8` is set when code `n` was tried
+    pub tried: String,
+    /// Number of codes tried
+    pub count: u32,
+}
+
+impl Party {
+    /// Add another code lock to the raid, e.g. a loot room or the garage door
+    pub async fn create_lock(
+        party_id: &str,
+        user_id: &str,
+        name: &str,
+        state: &AppState,
+    ) -> Result<PartyEvent, sqlx::Error> {
+        let random_number = rand::rng().random_range(0..u64::MAX);
+        let lock_id = Sqids::default().encode(&[2, random_number]).unwrap();
+
+        PartyEvent::create(
+            party_id,
+            user_id,
+            PartyEventData::PartyLockCreated(PartyEventLockCreated {
+                lock_id,
+                name: name.to_string(),
+            }),
+            state,
+        )
+        .await
+    }
+}
@@ -1,16 +1,51 @@
-use std::{collections::{HashMap, HashSet}, ops::Deref, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use chrono::{DateTime, Utc};
-use event::{PartyEvent, PartyEventData, PartyEventJoinLeave, PartyEventSettingChanged};
+use event::{
+    PartyEvent, PartyEventCodeFound, PartyEventCodesAssigned, PartyEventCodesSubmitted,
+    PartyEventData, PartyEventJoinLeave, PartyEventLegacySettingChanged,
+    PartyEventListOrderChanged, PartyEventLockCreated, PartyEventMemberKicked,
+    PartyEventOwnerChanged, PartyEventRoleChanged, PartyEventSecretRotated,
+};
+use invite::PartyInvite;
+use lease::PartyCodeLease;
+use lock::{PartyLockState, DEFAULT_LOCK_ID, DEFAULT_LOCK_NAME};
+use role::PartyRole;
+use settings::{PartyGuestPolicy, PartySetting, PartyStateSettings};
+use snapshot::PartySnapshot;
+use summary::PartyFoundCode;
 use poem_openapi::Object;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sqids::Sqids;
-use tracing::info;
+use sqlx::{Postgres, Transaction};
+use tracing::{info, warn};
 
-use crate::{state::AppState, util::generate_secret};
+use crate::{
+    models::{
+        codes::{load_code_sequence, CodeListOrderEntry},
+        user::is_guest_id,
+    },
+    state::AppState,
+    util::generate_secret,
+};
 
+pub mod bitmap;
 pub mod event;
+pub mod export;
+pub mod hub;
+pub mod invite;
+pub mod lease;
+pub mod lifecycle;
+pub mod listing;
+pub mod lock;
+pub mod role;
+pub mod settings;
+pub mod snapshot;
+pub mod summary;
 
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct Party {
@@ -18,6 +53,11 @@ pub struct Party {
     pub owner_id: String,
     pub party_secret: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub cracked_at: Option<DateTime<Utc>>,
+    /// The code that opened the lock
+    pub cracked_code: Option<String>,
+    /// Archived parties are read-only
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
 impl Party {
@@ -45,6 +85,7 @@ impl Party {
             PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
                 user_id: party.owner_id.clone(),
                 is_join: true,
+                invite_id: None,
             }),
             state,
         )
@@ -61,6 +102,253 @@ impl Party {
         Ok(party)
     }
 
+    /// Hand ownership of the party to another member
+    ///
+    /// Updates the party row and emits the event in one transaction,
+    /// returns `None` if `from_user_id` does not own the party (anymore).
+    pub async fn transfer_ownership(
+        party_id: &str,
+        from_user_id: &str,
+        to_user_id: &str,
+        state: &AppState,
+    ) -> Result<Option<(Self, PartyEvent)>, sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        let Some(party) =
+            Party::transfer_ownership_in_tx(&mut tx, party_id, from_user_id, to_user_id).await?
+        else {
+            return Ok(None);
+        };
+
+        let event = PartyEvent::create_in_tx(
+            &mut tx,
+            party_id,
+            from_user_id,
+            PartyEventData::PartyOwnerChanged(PartyEventOwnerChanged {
+                owner_id: to_user_id.to_string(),
+            }),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        state.party_hub.publish(&event);
+
+        Ok(Some((party, event)))
+    }
+
+    async fn transfer_ownership_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        from_user_id: &str,
+        to_user_id: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            "UPDATE parties SET owner_id = $3 WHERE party_id = $1 AND owner_id = $2 RETURNING *",
+            party_id,
+            from_user_id,
+            to_user_id
+        )
+        .fetch_optional(&mut **tx)
+        .await
+    }
+
+    /// Remove a user from the party
+    ///
+    /// When the owner leaves, ownership passes to the longest-standing remaining member
+    /// so the party stays manageable. An owner leaving as the last member archives the party.
+    pub async fn leave(
+        party_id: &str,
+        user_id: &str,
+        state: &AppState,
+    ) -> Result<Vec<PartyEvent>, Arc<sqlx::Error>> {
+        let mut tx = state.database.pool.begin().await.map_err(Arc::new)?;
+
+        // read the members under the party lock so a concurrent leave can't pick the same successor
+        PartyEvent::lock_party_in_tx(&mut tx, party_id)
+            .await
+            .map_err(Arc::new)?;
+
+        let party_state = Party::get_party_state_in_tx(&mut tx, party_id, state).await?;
+
+        let mut events = Vec::new();
+
+        events.push(
+            PartyEvent::create_in_tx(
+                &mut tx,
+                party_id,
+                user_id,
+                PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                    user_id: user_id.to_string(),
+                    is_join: false,
+                    invite_id: None,
+                }),
+            )
+            .await
+            .map_err(Arc::new)?,
+        );
+
+        if party_state.owner_id.as_deref() == Some(user_id) {
+            match party_state.longest_standing_member(user_id) {
+                Some(successor) => {
+                    if Party::transfer_ownership_in_tx(&mut tx, party_id, user_id, successor)
+                        .await
+                        .map_err(Arc::new)?
+                        .is_some()
+                    {
+                        events.push(
+                            PartyEvent::create_in_tx(
+                                &mut tx,
+                                party_id,
+                                user_id,
+                                PartyEventData::PartyOwnerChanged(PartyEventOwnerChanged {
+                                    owner_id: successor.to_string(),
+                                }),
+                            )
+                            .await
+                            .map_err(Arc::new)?,
+                        );
+                    }
+                }
+                None => {
+                    if let Some((_, event)) = Party::archive_in_tx(&mut tx, party_id, user_id, true)
+                        .await
+                        .map_err(Arc::new)?
+                    {
+                        events.push(event);
+                    }
+                }
+            }
+        }
+
+        tx.commit().await.map_err(Arc::new)?;
+
+        for event in &events {
+            state.party_hub.publish(event);
+        }
+
+        Ok(events)
+    }
+
+    /// Kick every guest member of a steam only party whose guest policy asks for it, the owner is left alone
+    /// Runs in the transaction of the setting change so the setting and the kicks are stored together,
+    /// the caller is responsible for publishing the events once the transaction is committed
+    pub async fn enforce_guest_policy_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        user_id: &str,
+        party_state: &PartyState,
+    ) -> Result<Vec<PartyEvent>, sqlx::Error> {
+        let settings = party_state.settings();
+
+        if !settings.steam_only || settings.guest_policy != PartyGuestPolicy::Kick {
+            return Ok(Vec::new());
+        }
+
+        let guests = party_state.members.keys().filter(|member| {
+            is_guest_id(member) && party_state.owner_id.as_deref() != Some(member.as_str())
+        });
+
+        let mut events = Vec::new();
+
+        for guest in guests {
+            events.push(
+                PartyEvent::create_in_tx(
+                    tx,
+                    party_id,
+                    user_id,
+                    PartyEventData::PartyMemberKicked(PartyEventMemberKicked {
+                        user_id: guest.clone(),
+                        ban: false,
+                    }),
+                )
+                .await?,
+            );
+        }
+
+        Ok(events)
+    }
+
+    /// Add a user to the party, redeeming the given invite in the same transaction
+    ///
+    /// Returns `None` when the invite can not be redeemed.
+    pub async fn join(
+        party_id: &str,
+        user_id: &str,
+        invite_id: Option<&str>,
+        state: &AppState,
+    ) -> Result<Option<PartyEvent>, sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        if let Some(invite_id) = invite_id {
+            if PartyInvite::redeem_in_tx(&mut tx, party_id, invite_id)
+                .await?
+                .is_none()
+            {
+                return Ok(None);
+            }
+        }
+
+        let event = PartyEvent::create_in_tx(
+            &mut tx,
+            party_id,
+            user_id,
+            PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                user_id: user_id.to_string(),
+                is_join: true,
+                invite_id: invite_id.map(str::to_string),
+            }),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        state.party_hub.publish(&event);
+
+        Ok(Some(event))
+    }
+
+    /// Replace the party secret, invalidating every invite link handed out so far
+    pub async fn rotate_secret(
+        party_id: &str,
+        user_id: &str,
+        state: &AppState,
+    ) -> Result<Self, sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        let party = sqlx::query_as!(
+            Self,
+            "UPDATE parties SET party_secret = $2 WHERE party_id = $1 RETURNING *",
+            party_id,
+            Some(generate_secret())
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let event = PartyEvent::create_in_tx(
+            &mut tx,
+            party_id,
+            user_id,
+            PartyEventData::PartySecretRotated(PartyEventSecretRotated {}),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        state.party_hub.publish(&event);
+
+        Ok(party)
+    }
+
+    /// Check a secret handed in by a user against the party secret
+    pub fn verify_secret(&self, secret: Option<&str>) -> bool {
+        match (&self.party_secret, secret) {
+            (Some(party_secret), Some(secret)) => party_secret == secret,
+            _ => false,
+        }
+    }
+
     pub async fn get_user_is_in_party(
         user_id: &str,
         party_id: &str,
@@ -75,58 +363,63 @@ impl Party {
             }
         };
 
-        info!("state: {:?}", state);
+        info!(
+            members = state.members.len(),
+            last_event_id = state.last_event_id,
+            "party state"
+        );
 
-        Ok(state.members.contains(user_id))
+        Ok(state.members.contains_key(user_id))
     }
 
+    /// Build the party state from the latest snapshot, replaying only the events after it
     pub async fn index_party_state(
         party_id: &str,
         state: &AppState,
+    ) -> Result<PartyState, sqlx::Error> {
+        let mut tx = state.database.pool.begin().await?;
+
+        let party_state = Party::index_party_state_in_tx(&mut tx, party_id).await?;
+
+        tx.commit().await?;
+
+        Ok(party_state)
+    }
+
+    async fn index_party_state_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
     ) -> Result<PartyState, sqlx::Error> {
         info!("index_party_state: {:?}", party_id);
 
-        let events = PartyEvent::get_join_leave_events(party_id, state).await?;
+        let mut party_state = match PartySnapshot::get_latest_in_tx(tx, party_id).await? {
+            Some(party_state) => party_state,
+            None => {
+                let party =
+                    sqlx::query_as!(Self, "SELECT * FROM parties WHERE party_id = $1", party_id)
+                        .fetch_optional(&mut **tx)
+                        .await?;
 
-        let mut state = PartyState {
-            members: HashSet::new(),
-            settings: PartyStateSettings {
-                private: false,
-                steam_only: false,
-                extra: HashMap::new(),
-            },
+                PartyState::new(party.map(|party| party.owner_id))
+            }
         };
 
+        let events =
+            PartyEvent::get_events_after_in_tx(tx, party_id, party_state.last_event_id).await?;
+
         for event in events {
-            match event.data {
-                PartyEventData::PartyJoinLeave(PartyEventJoinLeave { user_id, is_join }) => {
-                    if is_join {
-                        state.members.insert(user_id);
-                    } else {
-                        state.members.remove(&user_id);
-                    }
-                }
-                PartyEventData::PartySettingChanged(PartyEventSettingChanged { setting, value }) => {
-                    if setting == "private" {
-                        state.settings.private = value.as_bool().unwrap_or(false);
-                    } else if setting == "steam_only" {
-                        state.settings.steam_only = value.as_bool().unwrap_or(false);
-                    } else {
-                        state.settings.extra.insert(setting, value);
-                    }
-                }
-                _ => {}
-            }
+            party_state.apply(event);
         }
 
-        Ok(state)
+        Ok(party_state)
     }
 
+    /// Get the party state, catching the cached state up with any events stored since it was built
     pub async fn get_party_state(
         party_id: &str,
         state: &AppState,
     ) -> Result<PartyState, Arc<sqlx::Error>> {
-        let state = state
+        let cached = state
             .cache
             .party_state
             .try_get_with(
@@ -135,26 +428,584 @@ impl Party {
             )
             .await;
 
-        match state {
-            Ok(state) => Ok(state),
+        let party_state = match cached {
+            Ok(party_state) => party_state,
             Err(e) => {
                 tracing::error!("Failed to get party state: {}", e);
-                Err(e)
+                return Err(e);
+            }
+        };
+
+        let events = PartyEvent::get_events_after(party_id, party_state.last_event_id, state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to catch up party state: {}", e);
+                Arc::new(e)
+            })?;
+
+        Ok(Party::catch_up_party_state(party_id, party_state, events, state).await)
+    }
+
+    /// `get_party_state` for callers holding the party lock
+    ///
+    /// Every read goes through `tx`, so the caller never waits for a second pool connection
+    /// while it holds one. A missing state is built here instead of through the cache loader,
+    /// which could be waiting on a connection of its own.
+    /// Call it before storing events in `tx`, the state it returns is cached.
+    pub async fn get_party_state_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+        state: &AppState,
+    ) -> Result<PartyState, Arc<sqlx::Error>> {
+        let party_state = match state.cache.party_state.get(party_id).await {
+            Some(party_state) => party_state,
+            None => {
+                let party_state =
+                    Party::index_party_state_in_tx(tx, party_id)
+                        .await
+                        .map_err(|e| {
+                            tracing::error!("Failed to get party state: {}", e);
+                            Arc::new(e)
+                        })?;
+
+                state
+                    .cache
+                    .party_state
+                    .insert(party_id.to_string(), party_state.clone())
+                    .await;
+
+                party_state
+            }
+        };
+
+        let events = PartyEvent::get_events_after_in_tx(tx, party_id, party_state.last_event_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to catch up party state: {}", e);
+                Arc::new(e)
+            })?;
+
+        Ok(Party::catch_up_party_state(party_id, party_state, events, state).await)
+    }
+
+    /// Apply the events stored since the state was built and cache the result
+    async fn catch_up_party_state(
+        party_id: &str,
+        mut party_state: PartyState,
+        events: Vec<PartyEvent>,
+        state: &AppState,
+    ) -> PartyState {
+        if !events.is_empty() {
+            for event in events {
+                party_state.apply(event);
             }
+
+            state
+                .cache
+                .party_state
+                .insert(party_id.to_string(), party_state.clone())
+                .await;
         }
+
+        party_state
+    }
+
+    /// The ordered, deduplicated codes a party works through
+    /// Derived from the party's latest list order so every member sees the same sequence
+    pub async fn code_sequence(
+        party_id: &str,
+        state: &AppState,
+    ) -> Result<Vec<String>, Arc<sqlx::Error>> {
+        let party_state = Party::get_party_state(party_id, state).await?;
+
+        load_code_sequence(&party_state.list_order, state)
+            .await
+            .map_err(Arc::new)
     }
 }
 
+/// Projection of a party's event log
+/// Kept in the cache and advanced one event at a time, see `Party::get_party_state`
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PartyState {
-    members: HashSet<String>,
+    owner_id: Option<String>,
+    /// Event id of each member's join keyed by user id, lower means longer standing
+    members: HashMap<String, i32>,
     settings: PartyStateSettings,
+    list_order: Vec<CodeListOrderEntry>,
+    /// Progress on every code lock of the raid keyed by lock id
+    locks: HashMap<String, PartyLockState>,
+    /// Number of codes submitted keyed by user id
+    user_code_counts: HashMap<String, u32>,
+    /// Active code leases keyed by user id
+    leases: HashMap<String, PartyCodeLease>,
+    /// Roles of members that are not the default, keyed by user id
+    #[serde(default)]
+    roles: HashMap<String, PartyRole>,
+    /// Users that were kicked with a ban and can not join again
+    #[serde(default)]
+    bans: HashSet<String>,
+    #[serde(default)]
+    archived: bool,
+    /// Id of the last event applied to this state
+    last_event_id: i32,
+    /// Id of the last event covered by a persisted snapshot
+    #[serde(skip)]
+    snapshot_event_id: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct PartyStateSettings {
-    pub private: bool,
-    pub steam_only: bool,
-    #[serde(flatten)]
-    pub extra: HashMap<String, serde_json::Value>,
+impl PartyState {
+    pub fn settings(&self) -> &PartyStateSettings {
+        &self.settings
+    }
+
+    pub fn list_order(&self) -> &[CodeListOrderEntry] {
+        &self.list_order
+    }
+
+    pub fn lock(&self, lock_id: &str) -> Option<&PartyLockState> {
+        self.locks.get(lock_id)
+    }
+
+    pub fn locks(&self) -> &HashMap<String, PartyLockState> {
+        &self.locks
+    }
+
+    /// Whether every lock of the raid was opened
+    pub fn all_locks_found(&self) -> bool {
+        self.locks.values().all(|lock| lock.found.is_some())
+    }
+
+    /// Id of the last event applied, newer events can be followed from here
+    pub fn last_event_id(&self) -> i32 {
+        self.last_event_id
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    pub fn is_banned(&self, user_id: &str) -> bool {
+        self.bans.contains(user_id)
+    }
+
+    /// The member that has been in the party the longest, not counting `except`
+    pub fn longest_standing_member(&self, except: &str) -> Option<&str> {
+        self.members
+            .iter()
+            .filter(|(user_id, _)| *user_id != except)
+            .min_by_key(|(_, joined_event_id)| **joined_event_id)
+            .map(|(user_id, _)| user_id.as_str())
+    }
+
+    /// Guests can only follow along in a steam only party, the owner is exempt
+    pub fn is_read_only(&self, user_id: &str) -> bool {
+        self.settings.steam_only
+            && is_guest_id(user_id)
+            && self.owner_id.as_deref() != Some(user_id)
+    }
+
+    /// Whether the user owns the party or currently takes part in it
+    pub fn is_owner_or_member(&self, user_id: &str) -> bool {
+        self.owner_id.as_deref() == Some(user_id) || self.members.contains_key(user_id)
+    }
+
+    /// Role of a member, `None` if the user is not in the party
+    pub fn role_of(&self, user_id: &str) -> Option<PartyRole> {
+        if !self.members.contains_key(user_id) {
+            return None;
+        }
+
+        if self.owner_id.as_deref() == Some(user_id) {
+            return Some(PartyRole::Owner);
+        }
+
+        Some(self.roles.get(user_id).copied().unwrap_or_default())
+    }
+
+    pub fn new(owner_id: Option<String>) -> Self {
+        Self {
+            owner_id,
+            members: HashMap::new(),
+            settings: PartyStateSettings::default(),
+            list_order: CodeListOrderEntry::default_order(),
+            locks: HashMap::from([(
+                DEFAULT_LOCK_ID.to_string(),
+                PartyLockState::new(DEFAULT_LOCK_NAME.to_string()),
+            )]),
+            user_code_counts: HashMap::new(),
+            leases: HashMap::new(),
+            roles: HashMap::new(),
+            bans: HashSet::new(),
+            archived: false,
+            last_event_id: 0,
+            snapshot_event_id: 0,
+        }
+    }
+
+    fn remove_member(&mut self, user_id: &str) {
+        // codes leased to a member that left go back into the pool
+        self.leases.remove(user_id);
+        self.roles.remove(user_id);
+        self.members.remove(user_id);
+    }
+
+    /// Apply a single event on top of the current state
+    /// Events at or before `last_event_id` were already applied and are skipped
+    pub fn apply(&mut self, event: PartyEvent) {
+        if event.event_id <= self.last_event_id {
+            return;
+        }
+
+        self.last_event_id = event.event_id;
+
+        match event.data {
+            PartyEventData::PartyOwnerChanged(PartyEventOwnerChanged { owner_id }) => {
+                self.owner_id = Some(owner_id);
+            }
+            PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                user_id, is_join, ..
+            }) => {
+                if is_join {
+                    self.members.entry(user_id).or_insert(event.event_id);
+                } else {
+                    self.remove_member(&user_id);
+                }
+            }
+            PartyEventData::PartyMemberKicked(PartyEventMemberKicked { user_id, ban }) => {
+                self.remove_member(&user_id);
+
+                if ban {
+                    self.bans.insert(user_id);
+                }
+            }
+            PartyEventData::PartySettingChanged(setting) => {
+                match PartySetting::try_from(&setting) {
+                    Ok(setting) => self.settings.apply(setting),
+                    Err(e) => warn!(
+                        "Skipping invalid setting in event {}: {}",
+                        event.event_id, e
+                    ),
+                }
+            }
+            PartyEventData::PartyLegacySettingChanged(PartyEventLegacySettingChanged {
+                setting,
+                value,
+            }) => {
+                self.settings.extra.insert(setting, value);
+            }
+            PartyEventData::PartyListOrderChanged(PartyEventListOrderChanged { order }) => {
+                self.list_order = order;
+            }
+            PartyEventData::PartyCodesSubmitted(PartyEventCodesSubmitted {
+                codes,
+                lock_id,
+                ..
+            }) => {
+                *self.user_code_counts.entry(event.user_id).or_default() += codes.len() as u32;
+
+                if let Some(lock) = self.locks.get_mut(&lock_id) {
+                    lock.try_codes(codes);
+                }
+            }
+            PartyEventData::PartyCodesAssigned(PartyEventCodesAssigned {
+                user_id,
+                codes,
+                expires_at,
+                lock_id,
+            }) => {
+                self.leases.insert(
+                    user_id,
+                    PartyCodeLease {
+                        codes,
+                        expires_at,
+                        lock_id,
+                    },
+                );
+            }
+            PartyEventData::PartyLockCreated(PartyEventLockCreated { lock_id, name }) => {
+                self.locks
+                    .entry(lock_id)
+                    .or_insert_with(|| PartyLockState::new(name));
+            }
+            PartyEventData::PartyRoleChanged(PartyEventRoleChanged { user_id, role }) => {
+                self.roles.insert(user_id, role);
+            }
+            PartyEventData::PartyCodeFound(PartyEventCodeFound {
+                user_id,
+                code,
+                attempt,
+                lock_id,
+            }) => {
+                let Some(lock) = self.locks.get_mut(&lock_id) else {
+                    return;
+                };
+
+                lock.found.get_or_insert(PartyFoundCode {
+                    user_id,
+                    code,
+                    attempt: Some(attempt),
+                    found_at: event.created_at,
+                });
+            }
+            PartyEventData::PartyArchived(_) => {
+                self.archived = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::party::{
+        event::{PartyEventArchived, PartyEventSettingChanged},
+        settings::PartySettingKey,
+    };
+
+    const OWNER: &str = "steam:1";
+    const RAIDER: &str = "steam:2";
+    const GUEST: &str = "guest:3";
+
+    fn event(event_id: i32, user_id: &str, data: PartyEventData) -> PartyEvent {
+        PartyEvent {
+            party_id: "party".to_string(),
+            event_id,
+            user_id: user_id.to_string(),
+            data,
+            created_at: Utc::now(),
+            idempotency_key: None,
+        }
+    }
+
+    fn join_leave(event_id: i32, user_id: &str, is_join: bool) -> PartyEvent {
+        event(
+            event_id,
+            user_id,
+            PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
+                user_id: user_id.to_string(),
+                is_join,
+                invite_id: None,
+            }),
+        )
+    }
+
+    fn submit(event_id: i32, user_id: &str, lock_id: &str, codes: &[&str]) -> PartyEvent {
+        event(
+            event_id,
+            user_id,
+            PartyEventData::PartyCodesSubmitted(PartyEventCodesSubmitted {
+                user_id: user_id.to_string(),
+                codes: codes.iter().map(|code| code.to_string()).collect(),
+                lock_id: lock_id.to_string(),
+            }),
+        )
+    }
+
+    fn party() -> PartyState {
+        let mut state = PartyState::new(Some(OWNER.to_string()));
+        state.apply(join_leave(1, OWNER, true));
+        state.apply(join_leave(2, RAIDER, true));
+        state
+    }
+
+    #[test]
+    fn apply_tracks_members_and_leases() {
+        let mut state = party();
+        assert!(state.members.contains_key(RAIDER));
+
+        state.apply(event(
+            3,
+            RAIDER,
+            PartyEventData::PartyCodesAssigned(PartyEventCodesAssigned {
+                user_id: RAIDER.to_string(),
+                codes: vec!["1234".to_string()],
+                expires_at: Utc::now() + Duration::minutes(5),
+                lock_id: DEFAULT_LOCK_ID.to_string(),
+            }),
+        ));
+        assert!(state.leases.contains_key(RAIDER));
+
+        // leaving hands the leased codes back
+        state.apply(join_leave(4, RAIDER, false));
+        assert!(!state.members.contains_key(RAIDER));
+        assert!(state.leases.is_empty());
+
+        state.apply(event(
+            5,
+            OWNER,
+            PartyEventData::PartyOwnerChanged(PartyEventOwnerChanged {
+                owner_id: RAIDER.to_string(),
+            }),
+        ));
+        assert_eq!(state.owner_id.as_deref(), Some(RAIDER));
+    }
+
+    #[test]
+    fn apply_tracks_roles() {
+        let mut state = party();
+
+        assert_eq!(state.role_of(OWNER), Some(PartyRole::Owner));
+        assert_eq!(state.role_of(RAIDER), Some(PartyRole::Raider));
+        assert_eq!(state.role_of("steam:3"), None);
+
+        state.apply(event(
+            3,
+            OWNER,
+            PartyEventData::PartyRoleChanged(PartyEventRoleChanged {
+                user_id: RAIDER.to_string(),
+                role: PartyRole::Moderator,
+            }),
+        ));
+        assert_eq!(state.role_of(RAIDER), Some(PartyRole::Moderator));
+
+        // leaving drops the role, joining again starts over as a raider
+        state.apply(join_leave(4, RAIDER, false));
+        assert_eq!(state.role_of(RAIDER), None);
+
+        state.apply(join_leave(5, RAIDER, true));
+        assert_eq!(state.role_of(RAIDER), Some(PartyRole::Raider));
+    }
+
+    #[test]
+    fn apply_kicks_and_bans() {
+        let mut state = party();
+        state.apply(join_leave(3, "steam:3", true));
+
+        for (event_id, user_id, ban) in [(4, RAIDER, true), (5, "steam:3", false)] {
+            state.apply(event(
+                event_id,
+                OWNER,
+                PartyEventData::PartyMemberKicked(PartyEventMemberKicked {
+                    user_id: user_id.to_string(),
+                    ban,
+                }),
+            ));
+            assert_eq!(state.role_of(user_id), None);
+        }
+
+        assert!(state.is_banned(RAIDER));
+        assert!(!state.is_banned("steam:3"));
+    }
+
+    #[test]
+    fn apply_keeps_the_first_found_code() {
+        let mut state = party();
+
+        for (event_id, code) in [(3, "1234"), (4, "4321")] {
+            state.apply(event(
+                event_id,
+                RAIDER,
+                PartyEventData::PartyCodeFound(PartyEventCodeFound {
+                    user_id: RAIDER.to_string(),
+                    code: code.to_string(),
+                    attempt: 7,
+                    lock_id: DEFAULT_LOCK_ID.to_string(),
+                }),
+            ));
+        }
+
+        let found = state.lock(DEFAULT_LOCK_ID).unwrap().found.as_ref().unwrap();
+        assert_eq!(found.code, "1234");
+        assert_eq!(found.attempt, Some(7));
+        assert!(state.all_locks_found());
+    }
+
+    #[test]
+    fn apply_settings_and_archive() {
+        let mut state = party();
+        state.apply(join_leave(3, GUEST, true));
+        assert!(!state.is_read_only(GUEST));
+
+        state.apply(event(
+            4,
+            OWNER,
+            PartyEventData::PartySettingChanged(PartyEventSettingChanged {
+                setting: PartySettingKey::SteamOnly,
+                value: json!(true),
+            }),
+        ));
+        // a value of the wrong type is skipped instead of failing the projection
+        state.apply(event(
+            5,
+            OWNER,
+            PartyEventData::PartySettingChanged(PartyEventSettingChanged {
+                setting: PartySettingKey::Private,
+                value: json!("yes"),
+            }),
+        ));
+
+        assert!(state.settings().steam_only);
+        assert!(!state.settings().private);
+        assert!(state.is_read_only(GUEST));
+        assert!(!state.is_read_only(RAIDER));
+
+        assert!(!state.is_archived());
+        state.apply(event(
+            6,
+            OWNER,
+            PartyEventData::PartyArchived(PartyEventArchived { automatic: false }),
+        ));
+        assert!(state.is_archived());
+    }
+
+    #[test]
+    fn longest_standing_member_goes_by_join_order() {
+        let mut state = party();
+        state.apply(join_leave(3, "steam:3", true));
+
+        assert_eq!(state.longest_standing_member(OWNER), Some(RAIDER));
+
+        // rejoining puts the member at the back
+        state.apply(join_leave(4, RAIDER, false));
+        state.apply(join_leave(5, RAIDER, true));
+        assert_eq!(state.longest_standing_member(OWNER), Some("steam:3"));
+    }
+
+    #[test]
+    fn apply_skips_events_already_applied() {
+        let mut state = party();
+
+        state.apply(submit(3, RAIDER, DEFAULT_LOCK_ID, &["1234"]));
+        state.apply(submit(3, RAIDER, DEFAULT_LOCK_ID, &["4321"]));
+        state.apply(join_leave(2, RAIDER, false));
+
+        assert_eq!(state.last_event_id, 3);
+        assert_eq!(state.lock(DEFAULT_LOCK_ID).unwrap().attempts, 1);
+        assert!(state.members.contains_key(RAIDER));
+    }
+
+    #[test]
+    fn apply_counts_attempts_per_lock() {
+        let mut state = party();
+
+        state.apply(event(
+            3,
+            OWNER,
+            PartyEventData::PartyLockCreated(PartyEventLockCreated {
+                lock_id: "garage".to_string(),
+                name: "Garage".to_string(),
+            }),
+        ));
+        state.apply(submit(4, RAIDER, DEFAULT_LOCK_ID, &["1234", "0000"]));
+        state.apply(submit(5, OWNER, DEFAULT_LOCK_ID, &["1234", "1111"]));
+        state.apply(submit(6, RAIDER, "garage", &["1234"]));
+        // codes for a lock that does not exist only count towards the member
+        state.apply(submit(7, RAIDER, "missing", &["2222"]));
+
+        let main = state.lock(DEFAULT_LOCK_ID).unwrap();
+        assert_eq!(main.attempts, 3);
+        assert!(main.tried_codes.contains("1111"));
+        assert_eq!(main.attempt_of("1234"), 3);
+        assert_eq!(main.attempt_of("9999"), 4);
+
+        assert_eq!(state.lock("garage").unwrap().attempts, 1);
+        assert_eq!(state.user_code_counts.get(RAIDER), Some(&4));
+        assert_eq!(state.user_code_counts.get(OWNER), Some(&2));
+        assert!(state.lock("missing").is_none());
+    }
 }
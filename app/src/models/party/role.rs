@@ -0,0 +1,133 @@
+use poem_openapi::Enum;
+use serde::{Deserialize, Serialize};
+
+use super::event::PartyEventData;
+
+/// Role of a member within a party
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum PartyRole {
+    /// Created the party, can do everything
+    Owner,
+    /// Helps running the party, can change settings and the list order
+    Moderator,
+    /// Tries codes, the role every member starts with
+    #[default]
+    Raider,
+    /// Can follow along and chat
+    Spectator,
+}
+
+impl PartyRole {
+    /// Whether a member with this role may submit the given event through the api
+    /// Events the server emits on its own can not be submitted by anyone
+    pub fn can_submit(&self, data: &PartyEventData) -> bool {
+        match data {
+            PartyEventData::PartyChatMessage(_) => true,
+            PartyEventData::PartyCodesSubmitted(_) | PartyEventData::PartyCursorUpdate(_) => {
+                self.can_try_codes()
+            }
+            PartyEventData::PartySettingChanged(_) | PartyEventData::PartyListOrderChanged(_) => {
+                matches!(self, PartyRole::Owner | PartyRole::Moderator)
+            }
+            PartyEventData::PartyCreated(_)
+            | PartyEventData::PartyOwnerChanged(_)
+            | PartyEventData::PartyJoinLeave(_)
+            | PartyEventData::PartyCodesAssigned(_)
+            | PartyEventData::PartySecretRotated(_)
+            | PartyEventData::PartyRoleChanged(_)
+            | PartyEventData::PartyMemberKicked(_)
+            | PartyEventData::PartyLegacySettingChanged(_)
+            | PartyEventData::PartyArchived(_)
+            | PartyEventData::PartyCodeFound(_)
+            | PartyEventData::PartyLockCreated(_) => false,
+        }
+    }
+
+    /// Whether a member with this role takes part in trying codes
+    pub fn can_try_codes(&self) -> bool {
+        matches!(
+            self,
+            PartyRole::Owner | PartyRole::Moderator | PartyRole::Raider
+        )
+    }
+
+    /// Whether a member with this role may kick a member with the `target` role
+    pub fn can_kick(&self, target: PartyRole) -> bool {
+        match self {
+            PartyRole::Owner => target != PartyRole::Owner,
+            PartyRole::Moderator => matches!(target, PartyRole::Raider | PartyRole::Spectator),
+            PartyRole::Raider | PartyRole::Spectator => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::party::{
+        event::{
+            PartyEventArchived, PartyEventChatMessage, PartyEventCodesSubmitted,
+            PartyEventRoleChanged, PartyEventSettingChanged,
+        },
+        settings::PartySettingKey,
+    };
+
+    fn chat() -> PartyEventData {
+        PartyEventData::PartyChatMessage(PartyEventChatMessage {
+            message: "hi".to_string(),
+        })
+    }
+
+    fn codes() -> PartyEventData {
+        PartyEventData::PartyCodesSubmitted(PartyEventCodesSubmitted {
+            user_id: String::new(),
+            codes: vec!["1234".to_string()],
+            lock_id: "default".to_string(),
+        })
+    }
+
+    fn setting() -> PartyEventData {
+        PartyEventData::PartySettingChanged(PartyEventSettingChanged {
+            setting: PartySettingKey::Private,
+            value: true.into(),
+        })
+    }
+
+    #[test]
+    fn can_submit_by_role() {
+        let promotion = PartyEventData::PartyRoleChanged(PartyEventRoleChanged {
+            user_id: "steam:1".to_string(),
+            role: PartyRole::Moderator,
+        });
+        let archived = PartyEventData::PartyArchived(PartyEventArchived { automatic: false });
+
+        for (role, chats, tries, configures) in [
+            (PartyRole::Owner, true, true, true),
+            (PartyRole::Moderator, true, true, true),
+            (PartyRole::Raider, true, true, false),
+            (PartyRole::Spectator, true, false, false),
+        ] {
+            assert_eq!(role.can_submit(&chat()), chats, "{:?} chat", role);
+            assert_eq!(role.can_submit(&codes()), tries, "{:?} codes", role);
+            assert_eq!(
+                role.can_submit(&setting()),
+                configures,
+                "{:?} setting",
+                role
+            );
+            assert!(!role.can_submit(&promotion), "{:?} role change", role);
+            assert!(!role.can_submit(&archived), "{:?} archive", role);
+        }
+    }
+
+    #[test]
+    fn can_kick_lower_roles() {
+        assert!(PartyRole::Owner.can_kick(PartyRole::Moderator));
+        assert!(!PartyRole::Owner.can_kick(PartyRole::Owner));
+        assert!(PartyRole::Moderator.can_kick(PartyRole::Spectator));
+        assert!(!PartyRole::Moderator.can_kick(PartyRole::Moderator));
+        assert!(!PartyRole::Raider.can_kick(PartyRole::Spectator));
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use poem::http::StatusCode;
+use poem_openapi::{Enum, Object};
+use serde::{Deserialize, Serialize};
+
+use super::{event::PartyEventSettingChanged, lease::MAX_LEASE_SIZE};
+
+/// Settings a party can change through `PartySettingChanged`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+#[oai(rename_all = "snake_case")]
+pub enum PartySettingKey {
+    /// `bool`, joining requires the party secret or an invite
+    Private,
+    /// `bool`, only steam accounts can take part
+    SteamOnly,
+    /// `"read_only"` or `"kick"`, see `PartyGuestPolicy`
+    GuestPolicy,
+    /// `{ lat, lng, map_id }` of the base being raided, or `null`
+    Location,
+    /// Number of codes handed out per assignment, 1 up to the lease size limit
+    BatchSize,
+    /// BattleMetrics id of the server the raid is on, or `null`
+    ServerId,
+    /// RFC 3339 time of the next wipe, or `null`
+    WipeTime,
+}
+
+/// A setting together with its typed value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "setting", content = "value", rename_all = "snake_case")]
+pub enum PartySetting {
+    Private(bool),
+    SteamOnly(bool),
+    GuestPolicy(PartyGuestPolicy),
+    Location(Option<PartyLocation>),
+    BatchSize(u32),
+    ServerId(Option<String>),
+    WipeTime(Option<DateTime<Utc>>),
+}
+
+impl TryFrom<&PartyEventSettingChanged> for PartySetting {
+    type Error = serde_json::Error;
+
+    fn try_from(event: &PartyEventSettingChanged) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::json!({
+            "setting": event.setting,
+            "value": event.value,
+        }))
+    }
+}
+
+impl PartySetting {
+    /// Check the value of a submitted setting
+    pub fn validate(&self) -> poem::Result<()> {
+        match self {
+            PartySetting::BatchSize(size) if *size == 0 || *size > MAX_LEASE_SIZE => {
+                Err(poem::Error::from_string(
+                    format!("Batch size must be between 1 and {}", MAX_LEASE_SIZE),
+                    StatusCode::BAD_REQUEST,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartyGuestPolicy {
+    /// Guests stay in the party but can not submit events
+    #[default]
+    ReadOnly,
+    /// Guests are kicked from the party
+    Kick,
+}
+
+/// Where on the map the raid takes place
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyLocation {
+    pub lat: f64,
+    pub lng: f64,
+    pub map_id: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct PartyStateSettings {
+    pub private: bool,
+    pub steam_only: bool,
+    /// What happens to guest members once the party is steam only
+    #[serde(default)]
+    pub guest_policy: PartyGuestPolicy,
+    #[serde(default)]
+    pub location: Option<PartyLocation>,
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    #[serde(default)]
+    pub server_id: Option<String>,
+    #[serde(default)]
+    pub wipe_time: Option<DateTime<Utc>>,
+    /// Settings stored before they were typed, kept so they stay readable
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl PartyStateSettings {
+    pub fn apply(&mut self, setting: PartySetting) {
+        match setting {
+            PartySetting::Private(private) => self.private = private,
+            PartySetting::SteamOnly(steam_only) => self.steam_only = steam_only,
+            PartySetting::GuestPolicy(guest_policy) => self.guest_policy = guest_policy,
+            PartySetting::Location(location) => self.location = location,
+            PartySetting::BatchSize(batch_size) => self.batch_size = Some(batch_size),
+            PartySetting::ServerId(server_id) => self.server_id = server_id,
+            PartySetting::WipeTime(wipe_time) => self.wipe_time = wipe_time,
+        }
+    }
+}
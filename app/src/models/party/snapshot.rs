@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use tracing::{info, warn};
+
+use super::PartyState;
+use crate::state::AppState;
+
+/// How often cached party states are checked for snapshotting
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Minimum number of events applied since the last snapshot before a new one is written
+const SNAPSHOT_MIN_EVENTS: i32 = 100;
+
+/// Version of the `PartyState` projection, bump this when `PartyState::apply` changes
+/// so snapshots built by the previous projection are replayed instead
+pub const PARTY_STATE_VERSION: i32 = 1;
+
+/// A persisted copy of the party state as of `event_id`
+#[derive(Debug)]
+pub struct PartySnapshot {
+    pub party_id: String,
+    pub event_id: i32,
+    pub state: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub state_version: i32,
+}
+
+impl PartySnapshot {
+    /// Load the most recent snapshot of a party
+    ///
+    /// Snapshots of another `PARTY_STATE_VERSION` or that no longer match the shape
+    /// of `PartyState` are ignored, the caller then falls back to a full replay.
+    pub async fn get_latest_in_tx(
+        tx: &mut Transaction<'_, Postgres>,
+        party_id: &str,
+    ) -> Result<Option<PartyState>, sqlx::Error> {
+        let snapshot = sqlx::query_as!(
+            PartySnapshot,
+            "SELECT * FROM party_snapshots WHERE party_id = $1 AND state_version = $2 ORDER BY event_id DESC LIMIT 1",
+            party_id,
+            PARTY_STATE_VERSION
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        let Some(snapshot) = snapshot else {
+            return Ok(None);
+        };
+
+        match serde_json::from_value::<PartyState>(snapshot.state) {
+            Ok(mut party_state) => {
+                party_state.snapshot_event_id = snapshot.event_id;
+                Ok(Some(party_state))
+            }
+            Err(e) => {
+                warn!(
+                    "Discarding incompatible snapshot of party {}: {}",
+                    party_id, e
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Store a snapshot of the party state and drop the older ones
+    pub async fn create(
+        party_id: &str,
+        party_state: &PartyState,
+        state: &AppState,
+    ) -> Result<(), sqlx::Error> {
+        let data =
+            serde_json::to_value(party_state).expect("Failed to serialize PartyState to JSON");
+
+        let mut tx = state.database.pool.begin().await?;
+
+        sqlx::query!(
+            "INSERT INTO party_snapshots (party_id, event_id, state, state_version) VALUES ($1, $2, $3, $4) ON CONFLICT (party_id, event_id) DO UPDATE SET state = EXCLUDED.state, state_version = EXCLUDED.state_version",
+            party_id,
+            party_state.last_event_id,
+            data,
+            PARTY_STATE_VERSION
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM party_snapshots WHERE party_id = $1 AND event_id < $2",
+            party_id,
+            party_state.last_event_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Snapshot every cached party state that moved far enough past its last snapshot
+    pub async fn snapshot_cached(state: &AppState) {
+        let pending: Vec<(String, PartyState)> = state
+            .cache
+            .party_state
+            .iter()
+            .filter(|(_, party_state)| {
+                party_state.last_event_id - party_state.snapshot_event_id >= SNAPSHOT_MIN_EVENTS
+            })
+            .map(|(party_id, party_state)| (party_id.to_string(), party_state))
+            .collect();
+
+        for (party_id, mut party_state) in pending {
+            if let Err(e) = PartySnapshot::create(&party_id, &party_state, state).await {
+                warn!("Failed to snapshot party {}: {}", party_id, e);
+                continue;
+            }
+
+            info!(
+                party_id,
+                event_id = party_state.last_event_id,
+                "party snapshot"
+            );
+
+            party_state.snapshot_event_id = party_state.last_event_id;
+            state.cache.party_state.insert(party_id, party_state).await;
+        }
+    }
+
+    pub async fn collect(state: &AppState) {
+        loop {
+            async_std::task::sleep(SNAPSHOT_INTERVAL).await;
+
+            PartySnapshot::snapshot_cached(state).await;
+        }
+    }
+}
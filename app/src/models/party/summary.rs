@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use poem_openapi::Object;
+use serde::{Deserialize, Serialize};
+
+use super::Party;
+use crate::state::AppState;
+
+/// The code that opened the lock and who found it
+#[derive(Debug, Clone, Serialize, Deserialize, Object)]
+pub struct PartyFoundCode {
+    pub user_id: String,
+    pub code: String,
+    /// Number of distinct codes the party tried on the lock up to and including this one
+    /// Unknown for parties that were marked cracked before this was tracked
+    pub attempt: Option<u32>,
+    pub found_at: DateTime<Utc>,
+}
+
+/// Outcome of a raid
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyRaidSummary {
+    pub party_id: String,
+    pub started_at: DateTime<Utc>,
+    /// Seconds from the start of the party until the last lock was opened, or until now if one is still closed
+    pub duration_secs: i64,
+    /// Number of distinct codes tried, summed over all locks
+    pub codes_tried: u32,
+    pub locks: Vec<PartyLockSummary>,
+    /// Most active member first
+    pub contributions: Vec<PartyContribution>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyLockSummary {
+    pub lock_id: String,
+    pub name: String,
+    /// Number of distinct codes tried on this lock
+    pub codes_tried: u32,
+    pub found: Option<PartyFoundCode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyContribution {
+    pub user_id: String,
+    /// Number of codes the member submitted, including codes someone else already tried
+    pub codes_submitted: u32,
+}
+
+impl Party {
+    pub async fn raid_summary(
+        party: &Party,
+        state: &AppState,
+    ) -> Result<PartyRaidSummary, Arc<sqlx::Error>> {
+        let party_state = Party::get_party_state(&party.party_id, state).await?;
+
+        let ended_at = if party_state.all_locks_found() {
+            party_state
+                .locks
+                .values()
+                .filter_map(|lock| lock.found.as_ref().map(|found| found.found_at))
+                .max()
+                .unwrap_or_else(Utc::now)
+        } else {
+            Utc::now()
+        };
+
+        let mut locks: Vec<PartyLockSummary> = party_state
+            .locks
+            .iter()
+            .map(|(lock_id, lock)| PartyLockSummary {
+                lock_id: lock_id.clone(),
+                name: lock.name.clone(),
+                codes_tried: lock.tried_codes.count(),
+                found: lock.found.clone(),
+            })
+            .collect();
+
+        locks.sort_by(|a, b| a.lock_id.cmp(&b.lock_id));
+
+        let mut contributions: Vec<PartyContribution> = party_state
+            .user_code_counts
+            .iter()
+            .map(|(user_id, codes_submitted)| PartyContribution {
+                user_id: user_id.clone(),
+                codes_submitted: *codes_submitted,
+            })
+            .collect();
+
+        contributions.sort_by(|a, b| {
+            b.codes_submitted
+                .cmp(&a.codes_submitted)
+                .then_with(|| a.user_id.cmp(&b.user_id))
+        });
+
+        Ok(PartyRaidSummary {
+            party_id: party.party_id.clone(),
+            started_at: party.created_at,
+            duration_secs: (ended_at - party.created_at).num_seconds(),
+            codes_tried: locks.iter().map(|lock| lock.codes_tried).sum(),
+            locks,
+            contributions,
+        })
+    }
+}
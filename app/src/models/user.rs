@@ -7,6 +7,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{server::auth::oauth::SteamUserProfile, state::AppState};
 
+pub fn is_guest_id(user_id: &str) -> bool {
+    user_id.starts_with("guest:")
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Object)]
 #[oai(example)]
 pub struct User {
@@ -32,6 +36,11 @@ impl Example for User {
 }
 
 impl User {
+    /// Guests sign in through `/auth/guest` and are not backed by a steam account
+    pub fn is_guest(&self) -> bool {
+        is_guest_id(&self.user_id)
+    }
+
     pub async fn authorize_by_steam_id(
         state: &AppState,
         steam_profile: &SteamUserProfile,
@@ -143,7 +143,7 @@ impl OAuthApi {
     /// Extract Steam ID from the claimed_id URL
     fn extract_steam_id(&self, claimed_id: &str) -> Option<String> {
         // Steam claimed IDs are in the format: https://steamcommunity.com/openid/id/{steamid}
-        claimed_id.split('/').last().map(|s| s.to_string())
+        claimed_id.split('/').next_back().map(|s| s.to_string())
     }
 
     /// Fetch user profile from Steam API using a Steam ID
@@ -0,0 +1,240 @@
+use poem::{middleware::SizeLimit, web::Data, Endpoint, EndpointExt, Result};
+use poem_openapi::param::{Path, Query};
+use poem_openapi::payload::PlainText;
+use poem_openapi::{payload::Json, ApiRequest, OpenApi};
+use reqwest::StatusCode;
+
+use crate::models::codes::custom::{
+    parse_csv, parse_plain_text, validate_codes, CustomCodeList, CustomCodeListOwner,
+    CustomCodeListSummary,
+};
+use crate::models::codes::{CodeList, CodeListSummary, CodesRejected};
+use crate::models::party::role::PartyRole;
+use crate::models::party::Party;
+use crate::models::user::User;
+use crate::server::auth::mw::AuthUser;
+use crate::server::ApiTags;
+use crate::state::AppState;
+
+pub struct CodesApi;
+
+/// Longest name of an uploaded list
+const CODE_LIST_NAME_MAX_LENGTH: usize = 64;
+
+/// Most entries read from an upload, before invalid codes and repeats are dropped
+const CODE_LIST_MAX_ENTRIES: usize = 20_000;
+
+/// Largest accepted upload body, room for `CODE_LIST_MAX_ENTRIES` codes with a few extra csv columns
+const CODE_LIST_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Codes of an upload, one per line, in the first column of a csv or as a json array of strings
+#[derive(ApiRequest)]
+enum CodeListUpload {
+    #[oai(content_type = "text/plain")]
+    PlainText(PlainText<String>),
+    #[oai(content_type = "text/csv")]
+    Csv(PlainText<String>),
+    Json(Json<Vec<String>>),
+}
+
+#[OpenApi]
+impl CodesApi {
+    /// /codes/lists
+    ///
+    /// Get all available code lists, without their codes
+    #[oai(path = "/codes/lists", method = "get", tag = "ApiTags::Codes")]
+    async fn lists(&self) -> Result<Json<Vec<CodeListSummary>>> {
+        Ok(Json(
+            CodeList::all().iter().map(CodeList::summary).collect(),
+        ))
+    }
+
+    /// /codes/lists/:name
+    ///
+    /// Get a code list by name, including its codes
+    #[oai(path = "/codes/lists/:name", method = "get", tag = "ApiTags::Codes")]
+    async fn list(&self, #[oai(style = "simple")] name: Path<String>) -> Result<Json<CodeList>> {
+        let list = CodeList::get_by_name(&name.0)
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(list.clone()))
+    }
+
+    /// /codes/custom
+    ///
+    /// Upload a code list, owned by the party when `party_id` is set and by the user otherwise
+    /// Only the owner and moderators can upload lists for a party
+    ///
+    /// Use it in a party by adding `{ name, reverse, list_id }` to the list order
+    #[oai(
+        path = "/codes/custom",
+        method = "post",
+        tag = "ApiTags::Codes",
+        transform = "upload_size_limit"
+    )]
+    async fn upload(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        name: Query<String>,
+        party_id: Query<Option<String>>,
+        body: CodeListUpload,
+    ) -> Result<Json<CustomCodeListSummary>> {
+        let user = user.require_user()?;
+
+        let name = name.0.trim();
+
+        if name.is_empty() || name.chars().count() > CODE_LIST_NAME_MAX_LENGTH {
+            return Err(poem::Error::from_string(
+                format!(
+                    "List names are 1 to {} characters",
+                    CODE_LIST_NAME_MAX_LENGTH
+                ),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        if let Some(party_id) = &party_id.0 {
+            require_list_manager(party_id, user, state.0).await?;
+        }
+
+        let codes = match body {
+            CodeListUpload::PlainText(body) => parse_plain_text(&body.0),
+            CodeListUpload::Csv(body) => parse_csv(&body.0),
+            CodeListUpload::Json(body) => body.0,
+        };
+
+        if codes.len() > CODE_LIST_MAX_ENTRIES {
+            return Err(CodesRejected::new(
+                &format!("Lists can have at most {} entries", CODE_LIST_MAX_ENTRIES),
+                vec![],
+            )
+            .into());
+        }
+
+        let codes = validate_codes(codes)?;
+
+        let owner = match &party_id.0 {
+            Some(party_id) => CustomCodeListOwner::Party(party_id),
+            None => CustomCodeListOwner::User(&user.user_id),
+        };
+
+        let list = CustomCodeList::create(name, owner, &user.user_id, codes, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error creating code list: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(list.summary()))
+    }
+
+    /// /codes/custom
+    ///
+    /// Get the uploaded lists of the user, or of the party when `party_id` is set, without their codes
+    #[oai(path = "/codes/custom", method = "get", tag = "ApiTags::Codes")]
+    async fn custom_lists(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        party_id: Query<Option<String>>,
+    ) -> Result<Json<Vec<CustomCodeListSummary>>> {
+        let user = user.require_user()?;
+
+        let owner = match &party_id.0 {
+            Some(party_id) => {
+                require_party_member(party_id, user, state.0).await?;
+                CustomCodeListOwner::Party(party_id)
+            }
+            None => CustomCodeListOwner::User(&user.user_id),
+        };
+
+        let lists = CustomCodeList::get_by_owner(owner, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting code lists: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(lists.iter().map(CustomCodeList::summary).collect()))
+    }
+
+    /// /codes/custom/:list_id
+    ///
+    /// Get an uploaded list including its codes, visible to its owner or the members of its party
+    #[oai(
+        path = "/codes/custom/:list_id",
+        method = "get",
+        tag = "ApiTags::Codes"
+    )]
+    async fn custom_list(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] list_id: Path<String>,
+    ) -> Result<Json<CustomCodeList>> {
+        let user = user.require_user()?;
+
+        let list = CustomCodeList::get_by_id(&list_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting code list: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        match &list.party_id {
+            Some(party_id) => require_party_member(party_id, user, state.0).await?,
+            None if list.user_id.as_deref() == Some(user.user_id.as_str()) => {}
+            None => return Err(poem::Error::from_status(StatusCode::NOT_FOUND)),
+        }
+
+        Ok(Json(list))
+    }
+}
+
+/// Rejects uploads above `CODE_LIST_MAX_BODY_BYTES` before the body is read
+fn upload_size_limit(ep: impl Endpoint) -> impl Endpoint {
+    ep.with(SizeLimit::new(CODE_LIST_MAX_BODY_BYTES))
+}
+
+async fn require_party_member(party_id: &str, user: &User, state: &AppState) -> Result<()> {
+    let party_state = Party::get_party_state(party_id, state).await.map_err(|e| {
+        tracing::error!("Error getting party state: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    if party_state.role_of(&user.user_id).is_none() {
+        return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+    }
+
+    Ok(())
+}
+
+/// Lists of a party are managed by its owner and moderators, while it is not archived
+async fn require_list_manager(party_id: &str, user: &User, state: &AppState) -> Result<()> {
+    let party_state = Party::get_party_state(party_id, state).await.map_err(|e| {
+        tracing::error!("Error getting party state: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    let role = party_state
+        .role_of(&user.user_id)
+        .ok_or_else(|| poem::Error::from_status(StatusCode::FORBIDDEN))?;
+
+    if party_state.is_archived() {
+        return Err(poem::Error::from_string(
+            "Lists can not be uploaded to an archived party",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    if !matches!(role, PartyRole::Owner | PartyRole::Moderator) {
+        return Err(poem::Error::from_string(
+            "Your role in this party can not upload code lists",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    Ok(())
+}
@@ -5,7 +5,7 @@ use governor::Quota;
 // use info::InfoApi;
 // use media::MediaApi;
 use opentelemetry::global;
-use party::PartyApi;
+use party::{invite::PartyInviteApi, PartyApi};
 use poem::{
     endpoint::StaticFilesEndpoint, get, handler, listener::TcpListener,
     middleware::OpenTelemetryMetrics, EndpointExt, Route, Server,
@@ -19,11 +19,13 @@ use tracing::info;
 use crate::state::AppState;
 use auth::{oauth::OAuthApi, AuthApi};
 use bm::BattleMetricsApi;
+use codes::CodesApi;
 use inventory::InventoryApi;
 use tracing_mw::TraceId;
 
 pub mod auth;
 pub mod bm;
+pub mod codes;
 pub mod inventory;
 pub mod maps;
 pub mod party;
@@ -34,6 +36,8 @@ pub mod tracing_mw;
 pub enum ApiTags {
     /// Party Related Operations
     Party,
+    /// Code List Related Operations
+    Codes,
     /// Maps Related Operations
     /// 
     /// This uses the RustMaps.com API to search for maps
@@ -53,6 +57,8 @@ pub enum ApiTags {
 fn get_api(state: AppState) -> impl OpenApi {
     (
         PartyApi,
+        PartyInviteApi,
+        CodesApi,
         MapsApi,
         AuthApi,
         OAuthApi::new(state.clone()),
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use poem::{web::Data, Result};
+use poem_openapi::param::Path;
+use poem_openapi::{payload::Json, Object, OpenApi};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::models::party::invite::PartyInvite;
+use crate::server::auth::mw::AuthUser;
+use crate::server::ApiTags;
+use crate::state::AppState;
+
+use super::require_party_owner;
+
+pub struct PartyInviteApi;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyInviteCreateRequest {
+    /// When the invite stops working, never if omitted
+    pub expires_at: Option<DateTime<Utc>>,
+    /// How many times the invite can be redeemed, unlimited if omitted
+    pub max_uses: Option<i32>,
+}
+
+#[OpenApi]
+impl PartyInviteApi {
+    /// /party/:party_id/invites
+    ///
+    /// Create an invite token for a party, only the owner can do this
+    #[oai(
+        path = "/party/:party_id/invites",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn create(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        body: Json<PartyInviteCreateRequest>,
+    ) -> Result<Json<PartyInvite>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        require_party_owner(&party_id.0, user, state.0).await?;
+
+        if body.max_uses.is_some_and(|max_uses| max_uses < 1) {
+            return Err(poem::Error::from_string(
+                "max_uses must be at least 1",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        if body
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(poem::Error::from_string(
+                "expires_at must be in the future",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let invite = PartyInvite::create(
+            &party_id.0,
+            &user.user_id,
+            body.expires_at,
+            body.max_uses,
+            state.0,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating invite: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(invite))
+    }
+
+    /// /party/:party_id/invites
+    ///
+    /// List the invite tokens of a party, only the owner can do this
+    #[oai(
+        path = "/party/:party_id/invites",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn list(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<Vec<PartyInvite>>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        require_party_owner(&party_id.0, user, state.0).await?;
+
+        let invites = PartyInvite::get_by_party(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting invites: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(invites))
+    }
+
+    /// /party/:party_id/invites/:invite_id
+    ///
+    /// Revoke an invite token, only the owner can do this
+    #[oai(
+        path = "/party/:party_id/invites/:invite_id",
+        method = "delete",
+        tag = "ApiTags::Party"
+    )]
+    async fn revoke(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] invite_id: Path<String>,
+    ) -> Result<Json<PartyInvite>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        require_party_owner(&party_id.0, user, state.0).await?;
+
+        let invite = PartyInvite::revoke(&party_id.0, &invite_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error revoking invite: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        Ok(Json(invite))
+    }
+}
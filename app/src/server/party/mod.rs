@@ -1,16 +1,42 @@
-use poem::{web::Data, Result};
-use poem_openapi::param::{Path, Query};
-use poem_openapi::{payload::Json, Object, OpenApi};
+use std::time::Duration;
+
+use futures::{stream::BoxStream, FutureExt, StreamExt};
+use poem::web::sse::Event;
+use poem::web::websocket::WebSocket;
+use poem::{web::Data, Body, Result};
+use poem_openapi::param::{Header, Path, Query};
+use poem_openapi::payload::EventStream;
+use poem_openapi::types::ToJSON;
+use poem_openapi::payload::Binary;
+use poem_openapi::{payload::Json, ApiResponse, Object, OpenApi};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
 
-use crate::models::party::event::{PartyEvent, PartyEventData, PartyEventJoinLeave, PartyEventSettingChanged};
-use crate::models::party::Party;
+use crate::models::codes::custom::CustomCodeList;
+use crate::models::codes::{is_valid_code, load_code_sequence, CodeListOrderEntry};
+use crate::models::party::event::{
+    PartyEvent, PartyEventCodesSubmitted, PartyEventCursorUpdate, PartyEventData, PartyEventFilter,
+    PartyEventMemberKicked, PartyEventRoleChanged, PartyEventType,
+};
+use crate::models::party::bitmap::CodeBitmap;
+use crate::models::party::export::{PartyExportFormat, PartyExportKind};
+use crate::models::party::lock::{default_lock_id, PartyLockProgress, PartyLockTried};
+use crate::models::party::role::PartyRole;
+use crate::models::party::listing::{PartyActivity, PartySummary};
+use crate::models::party::summary::PartyRaidSummary;
+use crate::models::party::{Party, PartyState};
+use crate::models::user::User;
 use crate::server::ApiTags;
 use crate::state::AppState;
 
 use super::auth::mw::AuthUser;
 
+pub mod invite;
+pub mod socket;
+
+use socket::PartySocketUpgrade;
+
 #[derive(Debug, Serialize, Deserialize, Object)]
 pub struct PartyApi;
 
@@ -25,6 +51,92 @@ pub struct PartyCreateRequest {
     // pub name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyOwnerRequest {
+    /// Member that becomes the new owner
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyCrackedRequest {
+    /// The code that opened the lock
+    pub code: String,
+    #[oai(default = "default_lock_id")]
+    pub lock_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyLockCreateRequest {
+    /// e.g. "TC room" or "Garage door"
+    pub name: String,
+}
+
+/// Longest name a lock can have
+const PARTY_LOCK_NAME_MAX_LENGTH: usize = 64;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyRoleRequest {
+    pub role: PartyRole,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyCodesResponse {
+    /// Total number of codes in the party's sequence
+    pub total: u32,
+    pub offset: u32,
+    pub codes: Vec<String>,
+}
+
+/// Maximum number of codes returned per page
+const PARTY_CODES_MAX_LIMIT: u32 = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyListResponse {
+    /// Total number of parties matching the query
+    pub total: u32,
+    pub offset: u32,
+    pub parties: Vec<PartySummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyEventPage {
+    pub events: Vec<PartyEvent>,
+    /// Id of the last event in the page, pass it again to continue paging
+    pub next_cursor: Option<i32>,
+    /// Whether more events match past this page
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartyProgressResponse {
+    /// Id of the last event included, pass it as the stream cursor to receive later submissions
+    pub event_id: i32,
+    pub locks: Vec<PartyLockTried>,
+}
+
+#[derive(ApiResponse)]
+pub enum PartyExportResponse {
+    /// `text/csv`, `application/json` or `application/x-ndjson` depending on the format
+    #[oai(status = 200)]
+    Ok(
+        Binary<Body>,
+        #[oai(header = "Content-Type")] String,
+        #[oai(header = "Content-Disposition")] String,
+    ),
+}
+
+/// Longest idempotency key accepted on event submission
+const IDEMPOTENCY_KEY_MAX_LENGTH: usize = 255;
+
+/// Maximum number of events returned per page
+const PARTY_EVENTS_MAX_LIMIT: u32 = 500;
+
+/// Most wake-ups an event stream takes in at once before paging
+const SUBSCRIBER_SIGNAL_BATCH: usize = 64;
+
+/// Maximum number of parties returned per page
+const PARTY_LIST_MAX_LIMIT: u32 = 50;
+
 #[OpenApi]
 impl PartyApi {
     /// /party
@@ -51,9 +163,75 @@ impl PartyApi {
         }))
     }
 
+    /// /party
+    ///
+    /// List the parties the authenticated user owns or has taken part in, most recently active first
+    #[oai(path = "/party", method = "get", tag = "ApiTags::Party")]
+    async fn list(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] offset: Query<Option<u32>>,
+        #[oai(style = "simple")] limit: Query<Option<u32>>,
+    ) -> Result<Json<PartyListResponse>> {
+        let user = user.require_user()?;
+
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(20).min(PARTY_LIST_MAX_LIMIT);
+
+        let (total, parties) = Party::get_by_user(&user.user_id, offset, limit, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error listing parties: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let parties = summarize_parties(parties, user, state.0).await?;
+
+        Ok(Json(PartyListResponse {
+            total: total as u32,
+            offset,
+            parties,
+        }))
+    }
+
+    /// /party/discover
+    ///
+    /// List the public parties raiding on a server, most recently active first
+    #[oai(path = "/party/discover", method = "get", tag = "ApiTags::Party")]
+    async fn discover(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] server_id: Query<String>,
+        #[oai(style = "simple")] offset: Query<Option<u32>>,
+        #[oai(style = "simple")] limit: Query<Option<u32>>,
+    ) -> Result<Json<PartyListResponse>> {
+        let user = user.require_user()?;
+
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(20).min(PARTY_LIST_MAX_LIMIT);
+
+        let (total, parties) = Party::get_discoverable(&server_id.0, offset, limit, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error discovering parties: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let parties = summarize_parties(parties, user, state.0).await?;
+
+        Ok(Json(PartyListResponse {
+            total: total as u32,
+            offset,
+            parties,
+        }))
+    }
+
     /// /party/:party_id/join
     ///
     /// Join a party
+    /// Private parties require either the party secret or an invite token
     #[oai(
         path = "/party/:party_id/join",
         method = "post",
@@ -64,168 +242,1378 @@ impl PartyApi {
         state: Data<&AppState>,
         user: AuthUser,
         #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] secret: Query<Option<String>>,
+        #[oai(style = "simple")] invite: Query<Option<String>>,
     ) -> Result<Json<serde_json::Value>> {
         tracing::info!("{:?}", party_id.0);
         let user = user.require_user()?;
 
-        let party = Party::get_by_id(&party_id.0, state.0).await.map_err(|e| {
-            tracing::error!("Error getting party: {:?}", e);
-            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-        })?;
-
-        if let Some(party) = party {
-            if Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
-                .await
-                .map_err(|e| {
-                    tracing::error!("Error getting user is in party: {:?}", e);
-                    poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-                })?
-            {
-                return Err(poem::Error::from_status(StatusCode::CONFLICT));
-            }
+        let party = Party::get_by_id(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
 
-            let event = PartyEvent::create(
-                &party_id.0,
-                &user.user_id,
-                PartyEventData::PartyJoinLeave(PartyEventJoinLeave {
-                    user_id: user.user_id.clone(),
-                    is_join: true,
-                }),
-                state.0,
-            )
+        let party_state = Party::get_party_state(&party_id.0, state.0)
             .await
             .map_err(|e| {
-                tracing::error!("Error creating event: {:?}", e);
+                tracing::error!("Error getting party state: {:?}", e);
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
-            if event.data.requires_cache_invalidation() {
-                tracing::info!("Invalidating cache for party: {:?}", party_id.0);
-                state.cache.party_state.invalidate(&party_id.0).await;
-            }
+        if party_state.is_archived() {
+            return Err(party_archived_error());
+        }
+
+        if party_state.is_banned(&user.user_id) {
+            return Err(poem::Error::from_string(
+                "You are banned from this party",
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        if party_state.settings().steam_only && user.is_guest() {
+            return Err(poem::Error::from_string(
+                "This party is only open to steam accounts",
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        // a valid invite grants access on its own, it is checked when it is redeemed
+        if invite.0.is_none()
+            && party_state.settings().private
+            && !party.verify_secret(secret.0.as_deref())
+        {
+            return Err(poem::Error::from_string(
+                "Invalid party secret",
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        if Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::CONFLICT));
         }
 
+        Party::join(&party_id.0, &user.user_id, invite.0.as_deref(), state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error joining party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| {
+                poem::Error::from_string("Invalid or expired invite", StatusCode::FORBIDDEN)
+            })?;
+
         Ok(Json(serde_json::json!({})))
     }
 
-    /// /party/:party_id
+    /// /party/:party_id/leave
     ///
-    /// Get a party by ID
-    #[oai(path = "/party/:party_id", method = "get", tag = "ApiTags::Party")]
-    async fn get(
+    /// Leave a party
+    /// When the owner leaves, ownership passes to the longest-standing member, a party left empty is archived
+    #[oai(
+        path = "/party/:party_id/leave",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn leave(
         &self,
         state: Data<&AppState>,
         user: AuthUser,
         #[oai(style = "simple")] party_id: Path<String>,
-    ) -> Result<Json<Party>> {
+    ) -> Result<Json<Vec<PartyEvent>>> {
         tracing::info!("{:?}", party_id.0);
         let user = user.require_user()?;
 
-        let party = Party::get_by_id(&party_id.0, state.0).await.map_err(|e| {
-            tracing::error!("Error getting party: {:?}", e);
-            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-        })?;
+        let party_state = Party::get_party_state(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party state: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
 
-        if let Some(party) = party {
-            if Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
-                .await
-                .map_err(|e| {
-                    tracing::error!("Error getting user is in party: {:?}", e);
-                    poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-                })?
-            {
-                Ok(Json(party))
-            } else {
-                Err(poem::Error::from_status(StatusCode::FORBIDDEN))
-            }
-        } else {
-            Err(poem::Error::from_status(StatusCode::NOT_FOUND))
+        if party_state.role_of(&user.user_id).is_none() {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        if party_state.is_archived() {
+            return Err(party_archived_error());
         }
+
+        let events = Party::leave(&party_id.0, &user.user_id, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error leaving party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(events))
     }
 
-    /// /party/:party_id/events
+    /// /party/:party_id/kick/:user_id
     ///
-    /// Get events for a party
+    /// Remove a member from the party, only the owner and moderators can do this
+    /// Moderators can only kick raiders and spectators, `ban` keeps the user from joining again
     #[oai(
-        path = "/party/:party_id/events",
-        method = "get",
+        path = "/party/:party_id/kick/:user_id",
+        method = "post",
         tag = "ApiTags::Party"
     )]
-    async fn get_events(
+    async fn kick(
         &self,
         state: Data<&AppState>,
         user: AuthUser,
         #[oai(style = "simple")] party_id: Path<String>,
-        #[oai(style = "simple")] cursor: Query<Option<i32>>,
-    ) -> Result<Json<Vec<PartyEvent>>> {
+        #[oai(style = "simple")] user_id: Path<String>,
+        #[oai(style = "simple")] ban: Query<Option<bool>>,
+    ) -> Result<Json<PartyEvent>> {
         tracing::info!("{:?}", party_id.0);
-
         let user = user.require_user()?;
 
-        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+        let party_state = Party::get_party_state(&party_id.0, state.0)
             .await
             .map_err(|e| {
-                tracing::error!("Error getting user is in party: {:?}", e);
+                tracing::error!("Error getting party state: {:?}", e);
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
-            })?
-        {
-            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+            })?;
+
+        let role = party_state
+            .role_of(&user.user_id)
+            .ok_or_else(|| poem::Error::from_status(StatusCode::FORBIDDEN))?;
+
+        if party_state.is_archived() {
+            return Err(party_archived_error());
+        }
+
+        let target = party_state
+            .role_of(&user_id.0)
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        if user_id.0 == user.user_id {
+            return Err(poem::Error::from_string(
+                "Use /party/:party_id/leave to leave the party",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        if !role.can_kick(target) {
+            return Err(poem::Error::from_string(
+                "Your role in this party can not kick this member",
+                StatusCode::FORBIDDEN,
+            ));
         }
 
-        let cursor = cursor.unwrap_or(0);
+        let event = PartyEvent::create(
+            &party_id.0,
+            &user.user_id,
+            PartyEventData::PartyMemberKicked(PartyEventMemberKicked {
+                user_id: user_id.0.clone(),
+                ban: ban.unwrap_or(false),
+            }),
+            state.0,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating event: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(event))
+    }
+
+    /// /party/:party_id/secret
+    ///
+    /// Rotate the party secret, only the owner can do this
+    /// Invite links handed out with the old secret stop working
+    #[oai(
+        path = "/party/:party_id/secret",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn rotate_secret(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<Party>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party = require_party_owner(&party_id.0, user, state.0).await?;
+
+        if party.archived_at.is_some() {
+            return Err(party_archived_error());
+        }
 
-        let events = PartyEvent::get_events_by_event_cursor(&party_id.0, cursor, state.0)
+        let party = Party::rotate_secret(&party_id.0, &user.user_id, state.0)
             .await
             .map_err(|e| {
-                tracing::error!("Error getting events: {:?}", e);
+                tracing::error!("Error rotating party secret: {:?}", e);
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
-        Ok(Json(events))
+        Ok(Json(party))
     }
 
-    /// /party/:party_id/events
+    /// /party/:party_id/owner
     ///
-    /// Submit an event to a party
+    /// Hand ownership of the party to another member, only the owner can do this
     #[oai(
-        path = "/party/:party_id/events",
+        path = "/party/:party_id/owner",
         method = "post",
         tag = "ApiTags::Party"
     )]
-    async fn submit_event(
+    async fn transfer_ownership(
         &self,
         state: Data<&AppState>,
         user: AuthUser,
         #[oai(style = "simple")] party_id: Path<String>,
-        body: Json<PartyEventData>,
-    ) -> Result<Json<PartyEvent>> {
+        body: Json<PartyOwnerRequest>,
+    ) -> Result<Json<Party>> {
         tracing::info!("{:?}", party_id.0);
-
         let user = user.require_user()?;
 
-        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+        let party = require_party_owner(&party_id.0, user, state.0).await?;
+
+        if party.archived_at.is_some() {
+            return Err(party_archived_error());
+        }
+
+        if body.user_id == user.user_id {
+            return Err(poem::Error::from_string(
+                "You already own this party",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        if !Party::get_user_is_in_party(&body.user_id, &party_id.0, state.0)
             .await
             .map_err(|e| {
                 tracing::error!("Error getting user is in party: {:?}", e);
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
             })?
         {
-            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+            return Err(poem::Error::from_string(
+                "The new owner has to be a member of the party",
+                StatusCode::BAD_REQUEST,
+            ));
         }
 
-        // check event data
-        if body.0.requires_cache_invalidation() {
-            tracing::info!("Invalidating cache for party: {:?}", party_id.0);
-            state.cache.party_state.invalidate(&party_id.0).await;
-        }
+        let (party, _) =
+            Party::transfer_ownership(&party_id.0, &user.user_id, &body.user_id, state.0)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error transferring ownership: {:?}", e);
+                    poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+                })?
+                .ok_or_else(|| poem::Error::from_status(StatusCode::CONFLICT))?;
+
+        Ok(Json(party))
+    }
+
+    /// /party/:party_id/cracked
+    ///
+    /// Record the code that opened a lock, any member that tries codes can do this
+    /// No codes can be submitted for the lock afterwards, the party is cracked once every lock is open
+    #[oai(
+        path = "/party/:party_id/cracked",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn mark_cracked(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        body: Json<PartyCrackedRequest>,
+    ) -> Result<Json<Party>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
 
-        let event = PartyEvent::create(&party_id.0, &user.user_id, body.0, state.0)
+        let party_state = Party::get_party_state(&party_id.0, state.0)
             .await
             .map_err(|e| {
-                tracing::error!("Error creating event: {:?}", e);
+                tracing::error!("Error getting party state: {:?}", e);
                 poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
             })?;
 
-        Ok(Json(event))
+        let role = party_state
+            .role_of(&user.user_id)
+            .ok_or_else(|| poem::Error::from_status(StatusCode::FORBIDDEN))?;
+
+        if party_state.is_archived() {
+            return Err(party_archived_error());
+        }
+
+        if !role.can_try_codes() || party_state.is_read_only(&user.user_id) {
+            return Err(poem::Error::from_string(
+                "Your role in this party can not submit codes",
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        if !is_valid_code(&body.code) {
+            return Err(poem::Error::from_string(
+                "Codes are 4 digits",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        require_open_lock(&party_state, &body.lock_id)?;
+
+        let (party, _) = Party::mark_cracked(
+            &party_id.0,
+            &user.user_id,
+            &body.lock_id,
+            &body.code,
+            state.0,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error marking party cracked: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?
+        .ok_or_else(code_found_error)?;
+
+        Ok(Json(party))
     }
+
+    /// /party/:party_id/locks
+    ///
+    /// Get the progress on every code lock of the raid
+    #[oai(
+        path = "/party/:party_id/locks",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn get_locks(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<Vec<PartyLockProgress>>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party_state = Party::get_party_state(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party state: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if party_state.role_of(&user.user_id).is_none() {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let sequence = load_code_sequence(party_state.list_order(), state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error loading code sequence: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+        let sequence_codes: CodeBitmap = sequence.iter().collect();
+
+        let mut locks: Vec<PartyLockProgress> = party_state
+            .locks()
+            .iter()
+            .map(|(lock_id, lock)| PartyLockProgress {
+                lock_id: lock_id.clone(),
+                name: lock.name.clone(),
+                tried: lock.tried_codes.intersection_count(&sequence_codes),
+                total: sequence.len() as u32,
+                found: lock.found.clone(),
+            })
+            .collect();
+
+        locks.sort_by(|a, b| a.lock_id.cmp(&b.lock_id));
+
+        Ok(Json(locks))
+    }
+
+    /// /party/:party_id/progress
+    ///
+    /// Get every code tried on each lock as a bitmap, follow the event stream from `event_id` to keep it current
+    #[oai(
+        path = "/party/:party_id/progress",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn get_progress(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<PartyProgressResponse>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party_state = Party::get_party_state(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party state: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if party_state.role_of(&user.user_id).is_none() {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let mut locks: Vec<PartyLockTried> = party_state
+            .locks()
+            .iter()
+            .map(|(lock_id, lock)| PartyLockTried {
+                lock_id: lock_id.clone(),
+                tried: lock.tried_codes.to_base64(),
+                count: lock.tried_codes.count(),
+            })
+            .collect();
+
+        locks.sort_by(|a, b| a.lock_id.cmp(&b.lock_id));
+
+        Ok(Json(PartyProgressResponse {
+            event_id: party_state.last_event_id(),
+            locks,
+        }))
+    }
+
+    /// /party/:party_id/locks
+    ///
+    /// Add another code lock to the raid, only the owner and moderators can do this
+    #[oai(
+        path = "/party/:party_id/locks",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn create_lock(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        body: Json<PartyLockCreateRequest>,
+    ) -> Result<Json<PartyEvent>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party_state = Party::get_party_state(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party state: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let role = party_state
+            .role_of(&user.user_id)
+            .ok_or_else(|| poem::Error::from_status(StatusCode::FORBIDDEN))?;
+
+        if party_state.is_archived() {
+            return Err(party_archived_error());
+        }
+
+        if !matches!(role, PartyRole::Owner | PartyRole::Moderator) {
+            return Err(poem::Error::from_string(
+                "Your role in this party can not add locks",
+                StatusCode::FORBIDDEN,
+            ));
+        }
+
+        let name = body.name.trim();
+
+        if name.is_empty() || name.len() > PARTY_LOCK_NAME_MAX_LENGTH {
+            return Err(poem::Error::from_string(
+                format!(
+                    "Lock names are 1 to {} characters",
+                    PARTY_LOCK_NAME_MAX_LENGTH
+                ),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let event = Party::create_lock(&party_id.0, &user.user_id, name, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error creating lock: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(event))
+    }
+
+    /// /party/:party_id/summary
+    ///
+    /// Summarize the raid: how long it took, how many codes were tried and who tried them
+    #[oai(
+        path = "/party/:party_id/summary",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn summary(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<PartyRaidSummary>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party = Party::get_by_id(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let summary = Party::raid_summary(&party, state.0).await.map_err(|e| {
+            tracing::error!("Error summarizing raid: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(summary))
+    }
+
+    /// /party/:party_id/archive
+    ///
+    /// Archive the party, only the owner can do this
+    /// Archived parties are read-only, their events can still be viewed
+    #[oai(
+        path = "/party/:party_id/archive",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn archive(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<Party>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        require_party_owner(&party_id.0, user, state.0).await?;
+
+        let (party, _) = Party::archive(&party_id.0, &user.user_id, false, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error archiving party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+            .ok_or_else(party_archived_error)?;
+
+        Ok(Json(party))
+    }
+
+    /// /party/:party_id
+    ///
+    /// Permanently delete the party and all of its events, only the owner can do this
+    #[oai(path = "/party/:party_id", method = "delete", tag = "ApiTags::Party")]
+    async fn delete(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<serde_json::Value>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        require_party_owner(&party_id.0, user, state.0).await?;
+
+        Party::delete(&party_id.0, state.0).await.map_err(|e| {
+            tracing::error!("Error deleting party: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(serde_json::json!({})))
+    }
+
+    /// /party/:party_id/members/:user_id/role
+    ///
+    /// Promote or demote a member, only the owner can do this
+    /// Ownership itself can not be handed over this way
+    #[oai(
+        path = "/party/:party_id/members/:user_id/role",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn set_role(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] user_id: Path<String>,
+        body: Json<PartyRoleRequest>,
+    ) -> Result<Json<PartyEvent>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party = require_party_owner(&party_id.0, user, state.0).await?;
+
+        if party.archived_at.is_some() {
+            return Err(party_archived_error());
+        }
+
+        let party_state = Party::get_party_state(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party state: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        match party_state.role_of(&user_id.0) {
+            None => return Err(poem::Error::from_status(StatusCode::NOT_FOUND)),
+            Some(PartyRole::Owner) => {
+                return Err(poem::Error::from_string(
+                    "The owner's role can not be changed",
+                    StatusCode::BAD_REQUEST,
+                ))
+            }
+            Some(_) => {}
+        }
+
+        if body.role == PartyRole::Owner {
+            return Err(poem::Error::from_string(
+                "Ownership can not be granted through a role change",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let event = PartyEvent::create(
+            &party_id.0,
+            &user.user_id,
+            PartyEventData::PartyRoleChanged(PartyEventRoleChanged {
+                user_id: user_id.0.clone(),
+                role: body.role,
+            }),
+            state.0,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("Error creating event: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        Ok(Json(event))
+    }
+
+    /// /party/:party_id
+    ///
+    /// Get a party by ID
+    #[oai(path = "/party/:party_id", method = "get", tag = "ApiTags::Party")]
+    async fn get(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+    ) -> Result<Json<Party>> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        let party = Party::get_by_id(&party_id.0, state.0).await.map_err(|e| {
+            tracing::error!("Error getting party: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+        if let Some(party) = party {
+            if Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error getting user is in party: {:?}", e);
+                    poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+                })?
+            {
+                Ok(Json(party))
+            } else {
+                Err(poem::Error::from_status(StatusCode::FORBIDDEN))
+            }
+        } else {
+            Err(poem::Error::from_status(StatusCode::NOT_FOUND))
+        }
+    }
+
+    /// /party/:party_id/codes
+    ///
+    /// Get a page of the party's ordered code sequence
+    #[oai(
+        path = "/party/:party_id/codes",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn get_codes(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] offset: Query<Option<u32>>,
+        #[oai(style = "simple")] limit: Query<Option<u32>>,
+    ) -> Result<Json<PartyCodesResponse>> {
+        tracing::info!("{:?}", party_id.0);
+
+        let user = user.require_user()?;
+
+        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let sequence = Party::code_sequence(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting code sequence: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        let offset = offset.unwrap_or(0);
+        let limit = limit.unwrap_or(100).min(PARTY_CODES_MAX_LIMIT);
+
+        let codes = sequence
+            .iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+
+        Ok(Json(PartyCodesResponse {
+            total: sequence.len() as u32,
+            offset,
+            codes,
+        }))
+    }
+
+    /// /party/:party_id/assign
+    ///
+    /// Lease the next batch of codes not yet tried on the lock to the authenticated user
+    /// Replaces the user's previous lease, codes that are not submitted before the lease expires go back into the pool
+    /// `size` defaults to the party's `batch_size` setting, `lock_id` to the party's first lock
+    #[oai(
+        path = "/party/:party_id/assign",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn assign_codes(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] size: Query<Option<u32>>,
+        #[oai(style = "simple")] lock_id: Query<Option<String>>,
+    ) -> Result<Json<PartyEvent>> {
+        tracing::info!("{:?}", party_id.0);
+
+        let user = user.require_user()?;
+
+        let party_state = Party::get_party_state(&party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting party state: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if party_state.role_of(&user.user_id).is_none() {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        if party_state.is_read_only(&user.user_id) {
+            return Err(guest_read_only_error());
+        }
+
+        if party_state.is_archived() {
+            return Err(party_archived_error());
+        }
+
+        let lock_id = lock_id.0.unwrap_or_else(default_lock_id);
+
+        require_open_lock(&party_state, &lock_id)?;
+
+        let size = size.or(party_state.settings().batch_size).unwrap_or(5);
+
+        if size == 0 {
+            return Err(poem::Error::from_string(
+                "Size must be at least 1",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+
+        let event = Party::assign_codes(&party_id.0, &user.user_id, &lock_id, size, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error assigning codes: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(event))
+    }
+
+    /// /party/:party_id/export
+    ///
+    /// Download the party's events, or with `kind=attempts` one row per submitted code, as csv, json or ndjson
+    /// The body is streamed while the events are read
+    #[oai(
+        path = "/party/:party_id/export",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn export(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        format: Query<PartyExportFormat>,
+        #[oai(default)] kind: Query<PartyExportKind>,
+    ) -> Result<PartyExportResponse> {
+        tracing::info!("{:?}", party_id.0);
+        let user = user.require_user()?;
+
+        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let kind_name = match kind.0 {
+            PartyExportKind::Events => "events",
+            PartyExportKind::Attempts => "attempts",
+        };
+        let (content_type, extension) = match format.0 {
+            PartyExportFormat::Csv => ("text/csv", "csv"),
+            PartyExportFormat::Json => ("application/json", "json"),
+            PartyExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+        };
+        let disposition = format!(
+            "attachment; filename=\"party-{}-{}.{}\"",
+            party_id.0, kind_name, extension
+        );
+
+        let body = Binary(Body::from_bytes_stream(Party::export(
+            party_id.0,
+            format.0,
+            kind.0,
+            state.0.clone(),
+        )));
+
+        Ok(PartyExportResponse::Ok(
+            body,
+            content_type.to_string(),
+            disposition,
+        ))
+    }
+
+    /// /party/:party_id/events
+    ///
+    /// Get a page of events for a party
+    /// Pages forward from `cursor` oldest first, or backward from `before` newest first
+    /// Pass `next_cursor` as the same parameter to get the next page
+    #[oai(
+        path = "/party/:party_id/events",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    async fn get_events(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] cursor: Query<Option<i32>>,
+        #[oai(style = "simple")] before: Query<Option<i32>>,
+        #[oai(style = "simple")] limit: Query<Option<u32>>,
+        #[oai(style = "simple")] user_id: Query<Option<String>>,
+        #[oai(name = "type", explode = true)] event_type: Query<Option<Vec<PartyEventType>>>,
+    ) -> Result<Json<PartyEventPage>> {
+        tracing::info!("{:?}", party_id.0);
+
+        let user = user.require_user()?;
+
+        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let filter = PartyEventFilter {
+            after: cursor.0,
+            before: before.0,
+            user_id: user_id.0,
+            types: event_type.0.unwrap_or_default(),
+        };
+        let limit = limit.unwrap_or(20).clamp(1, PARTY_EVENTS_MAX_LIMIT);
+
+        let (events, has_more) = PartyEvent::get_events_page(&party_id.0, &filter, limit, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting events: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        Ok(Json(PartyEventPage {
+            next_cursor: events.last().map(|event| event.event_id),
+            has_more,
+            events,
+        }))
+    }
+
+    /// /party/:party_id/events/stream
+    ///
+    /// Stream events for a party as Server-Sent Events
+    /// Replays every event after the `Last-Event-ID` header (or `cursor`) and then pushes new events as they are created
+    /// The stream ends once the user leaves or is kicked from the party
+    #[oai(
+        path = "/party/:party_id/events/stream",
+        method = "get",
+        tag = "ApiTags::Party"
+    )]
+    async fn stream_events(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] cursor: Query<Option<i32>>,
+        #[oai(name = "Last-Event-ID")] last_event_id: Header<Option<i32>>,
+    ) -> Result<EventStream<BoxStream<'static, PartyEvent>>> {
+        tracing::info!("{:?}", party_id.0);
+
+        let user = user.require_user()?;
+
+        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let cursor = last_event_id.0.or(cursor.0).unwrap_or(0);
+
+        // Subscribe before paging so no event slips in between the two
+        // Published events only wake the stream up, every page is read from the stream's own cursor
+        let signals = state
+            .party_hub
+            .subscribe(&party_id.0)
+            .ready_chunks(SUBSCRIBER_SIGNAL_BATCH);
+
+        let stream_state = state.0.clone();
+        let stream_party_id = party_id.0.clone();
+        let user_id = user.user_id.clone();
+        let events = futures::stream::unfold(Some((cursor, Some(signals))), move |stream_cursor| {
+            let state = stream_state.clone();
+            let party_id = stream_party_id.clone();
+            let user_id = user_id.clone();
+
+            async move {
+                let (cursor, mut signals) = stream_cursor?;
+
+                loop {
+                    let filter = PartyEventFilter {
+                        after: Some(cursor),
+                        ..Default::default()
+                    };
+
+                    let mut events = match PartyEvent::get_events_page(
+                        &party_id,
+                        &filter,
+                        PARTY_EVENTS_MAX_LIMIT,
+                        &state,
+                    )
+                    .await
+                    {
+                        Ok((events, _)) => events,
+                        Err(e) => {
+                            tracing::error!("Error paging events: {:?}", e);
+                            return None;
+                        }
+                    };
+
+                    if let Some(last) = events.last() {
+                        let cursor = last.event_id;
+
+                        // end the stream right after the member's own removal is delivered
+                        if let Some(removal) = events
+                            .iter()
+                            .position(|event| event.data.removes_member(&user_id))
+                        {
+                            events.truncate(removal + 1);
+                            return Some((futures::stream::iter(events), None));
+                        }
+
+                        return Some((futures::stream::iter(events), Some((cursor, signals))));
+                    }
+
+                    // caught up, wait for the next event to be published
+                    // once the hub drops the subscriber, catch up one last time and end
+                    match signals.as_mut() {
+                        Some(receiver) => {
+                            if receiver.next().await.is_none() {
+                                signals = None;
+                            }
+                        }
+                        None => return None,
+                    }
+                }
+            }
+        })
+        .flatten()
+        .boxed();
+
+        Ok(EventStream::new(events)
+            .keep_alive(Duration::from_secs(15))
+            .to_event(|event| {
+                Event::message(event.to_json_string()).id(event.event_id.to_string())
+            }))
+    }
+
+    /// /party/:party_id/events
+    ///
+    /// Submit an event to a party
+    /// Retrying with the same `Idempotency-Key` returns the event stored by the first attempt
+    #[oai(
+        path = "/party/:party_id/events",
+        method = "post",
+        tag = "ApiTags::Party"
+    )]
+    async fn submit_event(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(name = "Idempotency-Key")] idempotency_key: Header<Option<String>>,
+        body: Json<PartyEventData>,
+    ) -> Result<Json<PartyEvent>> {
+        tracing::info!("{:?}", party_id.0);
+
+        let user = user.require_user()?;
+
+        let event = submit_party_event(
+            &party_id.0,
+            user,
+            body.0,
+            idempotency_key.0.as_deref(),
+            state.0,
+        )
+        .await?;
+
+        Ok(Json(event))
+    }
+
+    /// /party/:party_id/ws
+    ///
+    /// Open a WebSocket channel to a party
+    /// Accepts event frames from the client and pushes every new party event back
+    /// The socket closes once the user leaves or is kicked from the party
+    /// Browsers cannot set headers on WebSocket requests, so the token may also be passed as `?token=`
+    #[oai(path = "/party/:party_id/ws", method = "get", tag = "ApiTags::Party")]
+    async fn socket(
+        &self,
+        state: Data<&AppState>,
+        user: AuthUser,
+        #[oai(style = "simple")] party_id: Path<String>,
+        #[oai(style = "simple")] token: Query<Option<String>>,
+        ws: WebSocket,
+    ) -> Result<PartySocketUpgrade> {
+        tracing::info!("{:?}", party_id.0);
+
+        let user = match (user, token.0) {
+            (AuthUser::User(user, _), _) => user,
+            (AuthUser::None(_), Some(token)) => User::verify_jwt(&token, state.0).await?,
+            (AuthUser::None(_), None) => {
+                return Err(poem::Error::from_status(StatusCode::UNAUTHORIZED))
+            }
+        };
+
+        if !Party::get_user_is_in_party(&user.user_id, &party_id.0, state.0)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting user is in party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?
+        {
+            return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let state = state.0.clone();
+        let party_id = party_id.0;
+
+        Ok(ws.on_upgrade(Box::new(move |socket| {
+            socket::handle(socket, party_id, user, state).boxed()
+        })))
+    }
+}
+
+async fn summarize_parties(
+    parties: Vec<PartyActivity>,
+    user: &User,
+    state: &AppState,
+) -> Result<Vec<PartySummary>> {
+    let mut summaries = Vec::with_capacity(parties.len());
+
+    for activity in parties {
+        let summary = Party::summary(activity, &user.user_id, state)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error summarizing party: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        summaries.extend(summary);
+    }
+
+    Ok(summaries)
+}
+
+/// Get the party, failing unless the user owns it
+async fn require_party_owner(party_id: &str, user: &User, state: &AppState) -> Result<Party> {
+    let party = Party::get_by_id(party_id, state)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting party: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?
+        .ok_or_else(|| poem::Error::from_status(StatusCode::NOT_FOUND))?;
+
+    if party.owner_id != user.user_id {
+        return Err(poem::Error::from_status(StatusCode::FORBIDDEN));
+    }
+
+    Ok(party)
+}
+
+/// Shared submission path for events coming in over HTTP and the party socket
+///
+/// With an idempotency key a retried submission returns the event stored by the first attempt.
+async fn submit_party_event(
+    party_id: &str,
+    user: &User,
+    mut data: PartyEventData,
+    idempotency_key: Option<&str>,
+    state: &AppState,
+) -> Result<PartyEvent> {
+    if let Some(idempotency_key) = idempotency_key {
+        if idempotency_key.is_empty() || idempotency_key.len() > IDEMPOTENCY_KEY_MAX_LENGTH {
+            return Err(poem::Error::from_string(
+                format!(
+                    "Idempotency keys are 1 to {} characters",
+                    IDEMPOTENCY_KEY_MAX_LENGTH
+                ),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+    }
+
+    let mut tx = state.database.pool.begin().await.map_err(|e| {
+        tracing::error!("Error starting transaction: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    // Checks run against the state under the party lock, up to the insert
+    PartyEvent::lock_party_in_tx(&mut tx, party_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error locking party: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+    // the first attempt may have changed the party in a way that would reject a retry
+    if let Some(idempotency_key) = idempotency_key {
+        let stored = PartyEvent::get_by_idempotency_key_in_tx(&mut tx, party_id, idempotency_key)
+            .await
+            .map_err(|e| {
+                tracing::error!("Error getting event by idempotency key: {:?}", e);
+                poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+            })?;
+
+        if let Some(event) = stored {
+            return retried_event(event, user);
+        }
+    }
+
+    let mut party_state = Party::get_party_state_in_tx(&mut tx, party_id, state)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting party state: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+    let role = party_state
+        .role_of(&user.user_id)
+        .ok_or_else(|| poem::Error::from_status(StatusCode::FORBIDDEN))?;
+
+    if party_state.is_archived() {
+        return Err(party_archived_error());
+    }
+
+    if party_state.is_read_only(&user.user_id) {
+        return Err(guest_read_only_error());
+    }
+
+    match &data {
+        PartyEventData::PartyCodesSubmitted(PartyEventCodesSubmitted { lock_id, .. })
+        | PartyEventData::PartyCursorUpdate(PartyEventCursorUpdate { lock_id, .. }) => {
+            require_open_lock(&party_state, lock_id)?;
+        }
+        _ => {}
+    }
+
+    // the submitter is whoever is authenticated, codes are checked once repeats are gone
+    if let PartyEventData::PartyCodesSubmitted(submitted) = &mut data {
+        submitted.user_id = user.user_id.clone();
+        submitted.dedupe();
+    }
+
+    if !role.can_submit(&data) {
+        return Err(poem::Error::from_string(
+            "Your role in this party can not submit this event",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    data.validate()?;
+
+    if let PartyEventData::PartyListOrderChanged(event) = &data {
+        require_usable_code_lists(&mut tx, party_id, user, &event.order, state).await?;
+    }
+
+    let event = PartyEvent::create_idempotent_in_tx(
+        &mut tx,
+        party_id,
+        &user.user_id,
+        data,
+        idempotency_key,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Error creating event: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    let mut events = vec![event.clone()];
+
+    // guests are kicked together with the setting that made the party steam only
+    if matches!(event.data, PartyEventData::PartySettingChanged(_)) {
+        party_state.apply(event.clone());
+
+        let kicks =
+            Party::enforce_guest_policy_in_tx(&mut tx, party_id, &user.user_id, &party_state)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Error kicking guests: {:?}", e);
+                    poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+                })?;
+
+        events.extend(kicks);
+    }
+
+    tx.commit().await.map_err(|e| {
+        tracing::error!("Error committing event: {:?}", e);
+        poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+    })?;
+
+    for event in &events {
+        state.party_hub.publish(event);
+    }
+
+    Ok(event)
+}
+
+/// Idempotency keys are unique per party, only the user that stored the event gets it back
+fn retried_event(event: PartyEvent, user: &User) -> Result<PartyEvent> {
+    if event.user_id != user.user_id {
+        return Err(poem::Error::from_string(
+            "Idempotency key already used for another event",
+            StatusCode::CONFLICT,
+        ));
+    }
+
+    Ok(event)
+}
+
+/// Uploaded lists in a list order have to exist and belong to the party or the submitter
+async fn require_usable_code_lists(
+    tx: &mut Transaction<'_, Postgres>,
+    party_id: &str,
+    user: &User,
+    order: &[CodeListOrderEntry],
+    state: &AppState,
+) -> Result<()> {
+    let lists = CustomCodeList::get_for_order_in_tx(tx, order, state)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error getting code lists: {:?}", e);
+            poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+
+    for list_id in order.iter().filter_map(|entry| entry.list_id.as_ref()) {
+        if !lists
+            .get(list_id)
+            .is_some_and(|list| list.usable_by(party_id, &user.user_id))
+        {
+            return Err(poem::Error::from_string(
+                format!("Unknown code list: {}", list_id),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn code_found_error() -> poem::Error {
+    poem::Error::from_string(
+        "The code of this lock was already found",
+        StatusCode::CONFLICT,
+    )
+}
+
+/// Codes can only be tried on locks of the party that are still closed
+fn require_open_lock(party_state: &PartyState, lock_id: &str) -> Result<()> {
+    let lock = party_state
+        .lock(lock_id)
+        .ok_or_else(|| poem::Error::from_string("Unknown lock", StatusCode::BAD_REQUEST))?;
+
+    if lock.found.is_some() {
+        return Err(code_found_error());
+    }
+
+    Ok(())
+}
+
+fn party_archived_error() -> poem::Error {
+    poem::Error::from_string("This party is archived", StatusCode::FORBIDDEN)
+}
+
+fn guest_read_only_error() -> poem::Error {
+    poem::Error::from_string(
+        "Guests can not take part in a steam only party",
+        StatusCode::FORBIDDEN,
+    )
 }
@@ -0,0 +1,138 @@
+use futures::{future, future::BoxFuture, stream, SinkExt, StreamExt};
+use poem::web::websocket::{Message, WebSocketStream, WebSocketUpgraded};
+use poem_openapi::types::{ParseFromJSON, ToJSON};
+use poem_openapi::{Object, Union};
+use serde::{Deserialize, Serialize};
+
+use crate::models::party::event::{PartyEvent, PartyEventData};
+use crate::models::user::User;
+use crate::state::AppState;
+
+use super::submit_party_event;
+
+pub type PartySocketUpgrade =
+    WebSocketUpgraded<Box<dyn FnOnce(WebSocketStream) -> BoxFuture<'static, ()> + Send + Sync>>;
+
+/// A frame sent by the client over the party socket
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartySocketRequest {
+    /// Client chosen id, echoed back in the matching ack or error frame
+    pub request_id: Option<String>,
+    /// Resending a frame with the same key returns the event stored the first time
+    pub idempotency_key: Option<String>,
+    pub data: PartyEventData,
+}
+
+/// A frame sent by the server over the party socket
+#[derive(Debug, Serialize, Deserialize, Union)]
+#[oai(discriminator_name = "type")]
+pub enum PartySocketMessage {
+    Event(PartyEvent),
+    Ack(PartySocketAck),
+    Error(PartySocketError),
+}
+
+/// Confirms that a request frame was stored as an event
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartySocketAck {
+    pub request_id: Option<String>,
+    pub event_id: i32,
+}
+
+/// Rejects a request frame, `code` follows the HTTP status the REST api would have returned
+#[derive(Debug, Serialize, Deserialize, Object)]
+pub struct PartySocketError {
+    pub request_id: Option<String>,
+    pub code: u16,
+    pub message: String,
+}
+
+enum SocketInput {
+    Frame(Message),
+    Event(PartyEvent),
+    Closed,
+}
+
+pub async fn handle(socket: WebSocketStream, party_id: String, user: User, state: AppState) {
+    tracing::info!("Party socket opened: {:?} {:?}", party_id, user.user_id);
+
+    let (mut sink, incoming) = socket.split();
+
+    let incoming = incoming
+        .take_while(|frame| future::ready(frame.is_ok()))
+        .filter_map(|frame| future::ready(frame.ok()))
+        .map(SocketInput::Frame)
+        .chain(stream::once(future::ready(SocketInput::Closed)));
+    let live = state
+        .party_hub
+        .subscribe_member(&party_id, &user.user_id)
+        .map(SocketInput::Event)
+        .chain(stream::once(future::ready(SocketInput::Closed)));
+
+    let mut inputs = stream::select(incoming, live);
+
+    while let Some(input) = inputs.next().await {
+        let reply = match input {
+            SocketInput::Frame(Message::Text(text)) => {
+                Some(handle_frame(&text, &party_id, &user, &state).await)
+            }
+            SocketInput::Frame(Message::Close(_)) | SocketInput::Closed => break,
+            SocketInput::Frame(_) => None,
+            SocketInput::Event(event) => Some(PartySocketMessage::Event(event)),
+        };
+
+        if let Some(reply) = reply {
+            if sink
+                .send(Message::Text(reply.to_json_string()))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+
+    tracing::info!("Party socket closed: {:?} {:?}", party_id, user.user_id);
+}
+
+async fn handle_frame(
+    text: &str,
+    party_id: &str,
+    user: &User,
+    state: &AppState,
+) -> PartySocketMessage {
+    let request = match serde_json::from_str(text)
+        .map_err(|e| e.to_string())
+        .and_then(|value| {
+            PartySocketRequest::parse_from_json(Some(value)).map_err(|e| e.into_message())
+        }) {
+        Ok(request) => request,
+        Err(message) => {
+            return PartySocketMessage::Error(PartySocketError {
+                request_id: None,
+                code: 400,
+                message,
+            })
+        }
+    };
+
+    match submit_party_event(
+        party_id,
+        user,
+        request.data,
+        request.idempotency_key.as_deref(),
+        state,
+    )
+    .await
+    {
+        Ok(event) => PartySocketMessage::Ack(PartySocketAck {
+            request_id: request.request_id,
+            event_id: event.event_id,
+        }),
+        Err(e) => PartySocketMessage::Error(PartySocketError {
+            request_id: request.request_id,
+            code: e.status().as_u16(),
+            message: e.to_string(),
+        }),
+    }
+}
@@ -50,6 +50,7 @@ where
 /// The endpoint wrapper produced by the TraceId middleware.
 pub struct TraceIdEndpoint<T, E> {
     inner: E,
+    #[allow(dead_code)]
     tracer: Arc<T>,
 }
 
@@ -1,4 +1,4 @@
-use crate::{cache::AppCache, database::Database};
+use crate::{cache::AppCache, database::Database, models::party::hub::PartyHub};
 use figment::{providers::Env, Figment};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -27,14 +27,29 @@ pub struct BattleMetricsConfig {
     pub api_key: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PartyConfig {
+    /// Parties without new events for this many days are archived
+    #[serde(default = "PartyConfig::default_archive_after_days")]
+    pub archive_after_days: i64,
+}
+
+impl PartyConfig {
+    fn default_archive_after_days() -> i64 {
+        14
+    }
+}
+
 pub struct AppStateInner {
     pub database: Database,
     pub steam_oauth_config: SteamOAuthConfig,
     pub battlemetrics_config: BattleMetricsConfig,
     pub jwt: JwtConfig,
+    pub party_config: PartyConfig,
 
     //
     pub cache: AppCache,
+    pub party_hub: PartyHub,
 }
 
 impl AppStateInner {
@@ -62,14 +77,23 @@ impl AppStateInner {
             .extract::<JwtConfig>()
             .expect("Failed to load JWT secret");
 
+        let party_config = Figment::new()
+            .merge(Env::prefixed("PARTY_"))
+            .extract::<PartyConfig>()
+            .expect("Failed to load party configuration");
+
         let cache = AppCache::new();
 
+        let party_hub = PartyHub::new();
+
         Self {
             database,
             steam_oauth_config,
             battlemetrics_config,
             jwt,
+            party_config,
             cache,
+            party_hub,
         }
     }
 }
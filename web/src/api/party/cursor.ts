@@ -38,6 +38,7 @@ export const usePartyCursor = (party_id: string) => {
                 type: 'PartyCodesSubmitted',
                 codes: remainingCodes,
                 user_id: 'deprecated',
+                lock_id: 'default',
             });
         }
 
@@ -56,6 +57,7 @@ export const usePartyCursor = (party_id: string) => {
             cursor: nextCursor.toString(),
             size: codeCount,
             user_id: 'deprecated',
+            lock_id: 'default',
         });
 
         return nextCursor;
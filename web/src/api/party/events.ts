@@ -298,7 +298,7 @@ class PartyEventsFetcher {
       .then((res) => {
         clearTimeout(timeoutId); // Clear the timeout as the request completed
 
-        const events = res.data as components['schemas']['PartyEvent'][];
+        const events = res.data.events;
 
         console.log(`Received ${events.length} events for cursor ${cursor === undefined ? 'initial' : cursor}`);
 
@@ -196,7 +196,7 @@ export const usePartySettings = (party_id: string) => {
         update: (key: keyof PartySettings, value: unknown) => {
             submitEvent({
                 type: 'PartySettingChanged',
-                setting: key as string,
+                setting: key as components['schemas']['PartySettingKey'],
                 value: value,
             });
         },
@@ -11,7 +11,32 @@ export interface paths {
             path?: never;
             cookie?: never;
         };
-        get?: never;
+        /**
+         * /party
+         * @description List the parties the authenticated user owns or has taken part in, most recently active first
+         */
+        get: {
+            parameters: {
+                query?: {
+                    offset?: number;
+                    limit?: number;
+                };
+                header?: never;
+                path?: never;
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyListResponse"];
+                    };
+                };
+            };
+        };
         put?: never;
         /**
          * /party
@@ -46,6 +71,48 @@ export interface paths {
         patch?: never;
         trace?: never;
     };
+    "/party/discover": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/discover
+         * @description List the public parties raiding on a server, most recently active first
+         */
+        get: {
+            parameters: {
+                query: {
+                    server_id: string;
+                    offset?: number;
+                    limit?: number;
+                };
+                header?: never;
+                path?: never;
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyListResponse"];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
     "/party/{party_id}/join": {
         parameters: {
             query?: never;
@@ -58,10 +125,14 @@ export interface paths {
         /**
          * /party/:party_id/join
          * @description Join a party
+         *     Private parties require either the party secret or an invite token
          */
         post: {
             parameters: {
-                query?: never;
+                query?: {
+                    secret?: string;
+                    invite?: string;
+                };
                 header?: never;
                 path: {
                     party_id: string;
@@ -86,7 +157,972 @@ export interface paths {
         patch?: never;
         trace?: never;
     };
-    "/party/{party_id}": {
+    "/party/{party_id}/leave": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/leave
+         * @description Leave a party
+         *     When the owner leaves, ownership passes to the longest-standing member, a party left empty is archived
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"][];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/kick/{user_id}": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/kick/:user_id
+         * @description Remove a member from the party, only the owner and moderators can do this
+         *     Moderators can only kick raiders and spectators, `ban` keeps the user from joining again
+         */
+        post: {
+            parameters: {
+                query?: {
+                    ban?: boolean;
+                };
+                header?: never;
+                path: {
+                    party_id: string;
+                    user_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/secret": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/secret
+         * @description Rotate the party secret, only the owner can do this
+         *     Invite links handed out with the old secret stop working
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["Party"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/owner": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/owner
+         * @description Hand ownership of the party to another member, only the owner can do this
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody: {
+                content: {
+                    "application/json; charset=utf-8": components["schemas"]["PartyOwnerRequest"];
+                };
+            };
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["Party"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/cracked": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/cracked
+         * @description Record the code that opened a lock, any member that tries codes can do this
+         *     No codes can be submitted for the lock afterwards, the party is cracked once every lock is open
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody: {
+                content: {
+                    "application/json; charset=utf-8": components["schemas"]["PartyCrackedRequest"];
+                };
+            };
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["Party"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/locks": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/locks
+         * @description Get the progress on every code lock of the raid
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyLockProgress"][];
+                    };
+                };
+            };
+        };
+        put?: never;
+        /**
+         * /party/:party_id/locks
+         * @description Add another code lock to the raid, only the owner and moderators can do this
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody: {
+                content: {
+                    "application/json; charset=utf-8": components["schemas"]["PartyLockCreateRequest"];
+                };
+            };
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/progress": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/progress
+         * @description Get every code tried on each lock as a bitmap, follow the event stream from `event_id` to keep it current
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyProgressResponse"];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/summary": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/summary
+         * @description Summarize the raid: how long it took, how many codes were tried and who tried them
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyRaidSummary"];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/archive": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/archive
+         * @description Archive the party, only the owner can do this
+         *     Archived parties are read-only, their events can still be viewed
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["Party"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id
+         * @description Get a party by ID
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["Party"];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        /**
+         * /party/:party_id
+         * @description Permanently delete the party and all of its events, only the owner can do this
+         */
+        delete: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": unknown;
+                    };
+                };
+            };
+        };
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/members/{user_id}/role": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/members/:user_id/role
+         * @description Promote or demote a member, only the owner can do this
+         *     Ownership itself can not be handed over this way
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                    user_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody: {
+                content: {
+                    "application/json; charset=utf-8": components["schemas"]["PartyRoleRequest"];
+                };
+            };
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/codes": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/codes
+         * @description Get a page of the party's ordered code sequence
+         */
+        get: {
+            parameters: {
+                query?: {
+                    offset?: number;
+                    limit?: number;
+                };
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyCodesResponse"];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/assign": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        /**
+         * /party/:party_id/assign
+         * @description Lease the next batch of codes not yet tried on the lock to the authenticated user
+         *     Replaces the user's previous lease, codes that are not submitted before the lease expires go back into the pool
+         *     `size` defaults to the party's `batch_size` setting, `lock_id` to the party's first lock
+         */
+        post: {
+            parameters: {
+                query?: {
+                    size?: number;
+                    lock_id?: string;
+                };
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/export": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/export
+         * @description Download the party's events, or with `kind=attempts` one row per submitted code, as csv, json or ndjson
+         *     The body is streamed while the events are read
+         */
+        get: {
+            parameters: {
+                query: {
+                    format: components["schemas"]["PartyExportFormat"];
+                    kind?: components["schemas"]["PartyExportKind"] & unknown;
+                };
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                /** @description `text/csv`, `application/json` or `application/x-ndjson` depending on the format */
+                200: {
+                    headers: {
+                        "CONTENT-TYPE": string;
+                        "CONTENT-DISPOSITION": string;
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/octet-stream": string;
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/events": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/events
+         * @description Get a page of events for a party
+         *     Pages forward from `cursor` oldest first, or backward from `before` newest first
+         *     Pass `next_cursor` as the same parameter to get the next page
+         */
+        get: {
+            parameters: {
+                query?: {
+                    cursor?: number;
+                    before?: number;
+                    limit?: number;
+                    user_id?: string;
+                    type?: components["schemas"]["PartyEventType"][];
+                };
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEventPage"];
+                    };
+                };
+            };
+        };
+        put?: never;
+        /**
+         * /party/:party_id/events
+         * @description Submit an event to a party
+         *     Retrying with the same `Idempotency-Key` returns the event stored by the first attempt
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: {
+                    "Idempotency-Key"?: string;
+                };
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody: {
+                content: {
+                    "application/json; charset=utf-8": components["schemas"]["PartyEventData"];
+                };
+            };
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/events/stream": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/events/stream
+         * @description Stream events for a party as Server-Sent Events
+         *     Replays every event after the `Last-Event-ID` header (or `cursor`) and then pushes new events as they are created
+         *     The stream ends once the user leaves or is kicked from the party
+         */
+        get: {
+            parameters: {
+                query?: {
+                    cursor?: number;
+                };
+                header?: {
+                    "Last-Event-ID"?: number;
+                };
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "text/event-stream": components["schemas"]["PartyEvent"][];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/ws": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/ws
+         * @description Open a WebSocket channel to a party
+         *     Accepts event frames from the client and pushes every new party event back
+         *     The socket closes once the user leaves or is kicked from the party
+         *     Browsers cannot set headers on WebSocket requests, so the token may also be passed as `?token=`
+         */
+        get: {
+            parameters: {
+                query?: {
+                    token?: string;
+                };
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                /** @description A websocket response */
+                101: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content?: never;
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/invites": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /party/:party_id/invites
+         * @description List the invite tokens of a party, only the owner can do this
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyInvite"][];
+                    };
+                };
+            };
+        };
+        put?: never;
+        /**
+         * /party/:party_id/invites
+         * @description Create an invite token for a party, only the owner can do this
+         */
+        post: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody: {
+                content: {
+                    "application/json; charset=utf-8": components["schemas"]["PartyInviteCreateRequest"];
+                };
+            };
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyInvite"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/party/{party_id}/invites/{invite_id}": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        get?: never;
+        put?: never;
+        post?: never;
+        /**
+         * /party/:party_id/invites/:invite_id
+         * @description Revoke an invite token, only the owner can do this
+         */
+        delete: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    party_id: string;
+                    invite_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["PartyInvite"];
+                    };
+                };
+            };
+        };
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/codes/lists": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /codes/lists
+         * @description Get all available code lists, without their codes
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path?: never;
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["CodeListSummary"][];
+                    };
+                };
+            };
+        };
+        put?: never;
+        post?: never;
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/codes/lists/{name}": {
         parameters: {
             query?: never;
             header?: never;
@@ -94,15 +1130,15 @@ export interface paths {
             cookie?: never;
         };
         /**
-         * /party/:party_id
-         * @description Get a party by ID
+         * /codes/lists/:name
+         * @description Get a code list by name, including its codes
          */
         get: {
             parameters: {
                 query?: never;
                 header?: never;
                 path: {
-                    party_id: string;
+                    name: string;
                 };
                 cookie?: never;
             };
@@ -113,7 +1149,7 @@ export interface paths {
                         [name: string]: unknown;
                     };
                     content: {
-                        "application/json; charset=utf-8": components["schemas"]["Party"];
+                        "application/json; charset=utf-8": components["schemas"]["CodeList"];
                     };
                 };
             };
@@ -126,7 +1162,7 @@ export interface paths {
         patch?: never;
         trace?: never;
     };
-    "/party/{party_id}/events": {
+    "/codes/custom": {
         parameters: {
             query?: never;
             header?: never;
@@ -134,18 +1170,16 @@ export interface paths {
             cookie?: never;
         };
         /**
-         * /party/:party_id/events
-         * @description Get events for a party
+         * /codes/custom
+         * @description Get the uploaded lists of the user, or of the party when `party_id` is set, without their codes
          */
         get: {
             parameters: {
                 query?: {
-                    cursor?: number;
+                    party_id?: string;
                 };
                 header?: never;
-                path: {
-                    party_id: string;
-                };
+                path?: never;
                 cookie?: never;
             };
             requestBody?: never;
@@ -155,28 +1189,35 @@ export interface paths {
                         [name: string]: unknown;
                     };
                     content: {
-                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"][];
+                        "application/json; charset=utf-8": components["schemas"]["CustomCodeListSummary"][];
                     };
                 };
             };
         };
         put?: never;
         /**
-         * /party/:party_id/events
-         * @description Submit an event to a party
+         * /codes/custom
+         * @description Upload a code list, owned by the party when `party_id` is set and by the user otherwise
+         *     Only the owner and moderators can upload lists for a party
+         *     
+         *     Use it in a party by adding `{ name, reverse, list_id }` to the list order
          */
         post: {
             parameters: {
-                query?: never;
-                header?: never;
-                path: {
-                    party_id: string;
+                query: {
+                    name: string;
+                    party_id?: string;
                 };
+                header?: never;
+                path?: never;
                 cookie?: never;
             };
+            /** @description Codes of an upload, one per line, in the first column of a csv or as a json array of strings */
             requestBody: {
                 content: {
-                    "application/json; charset=utf-8": components["schemas"]["PartyEventData"];
+                    "text/plain": string;
+                    "text/csv": string;
+                    "application/json; charset=utf-8": string[];
                 };
             };
             responses: {
@@ -185,11 +1226,51 @@ export interface paths {
                         [name: string]: unknown;
                     };
                     content: {
-                        "application/json; charset=utf-8": components["schemas"]["PartyEvent"];
+                        "application/json; charset=utf-8": components["schemas"]["CustomCodeListSummary"];
+                    };
+                };
+            };
+        };
+        delete?: never;
+        options?: never;
+        head?: never;
+        patch?: never;
+        trace?: never;
+    };
+    "/codes/custom/{list_id}": {
+        parameters: {
+            query?: never;
+            header?: never;
+            path?: never;
+            cookie?: never;
+        };
+        /**
+         * /codes/custom/:list_id
+         * @description Get an uploaded list including its codes, visible to its owner or the members of its party
+         */
+        get: {
+            parameters: {
+                query?: never;
+                header?: never;
+                path: {
+                    list_id: string;
+                };
+                cookie?: never;
+            };
+            requestBody?: never;
+            responses: {
+                200: {
+                    headers: {
+                        [name: string]: unknown;
+                    };
+                    content: {
+                        "application/json; charset=utf-8": components["schemas"]["CustomCodeList"];
                     };
                 };
             };
         };
+        put?: never;
+        post?: never;
         delete?: never;
         options?: never;
         head?: never;
@@ -600,6 +1681,73 @@ export interface components {
         BattleMetricsRecentServers: {
             servers: components["schemas"]["BattleMetricsRecentServer"][];
         };
+        /**
+         * CodeList
+         * @description A named, ordered list of lock codes to work through
+         */
+        CodeList: {
+            name: string;
+            description?: string;
+            source?: string;
+            /** Format: uint32 */
+            size: number;
+            codes: string[];
+        };
+        /**
+         * CodeListOrderEntry
+         * @description A single entry of a party's list order
+         */
+        CodeListOrderEntry: {
+            /** @description Name of the list in the catalog */
+            name: string;
+            /** @description Whether to walk the list back to front */
+            reverse: boolean;
+            /** @description Id of an uploaded list to walk instead of a catalog list, `name` is then only for display */
+            list_id?: string;
+        };
+        /**
+         * CodeListSummary
+         * @description Metadata of a code list without the codes themselves
+         */
+        CodeListSummary: {
+            name: string;
+            description?: string;
+            source?: string;
+            /** Format: uint32 */
+            size: number;
+        };
+        /**
+         * CustomCodeList
+         * @description A code list uploaded by a user, owned by either that user or a party
+         *     Lists can not be changed after the upload, so parties walking them keep a stable sequence
+         */
+        CustomCodeList: {
+            list_id: string;
+            name: string;
+            /** @description Set when the list belongs to a user */
+            user_id?: string;
+            /** @description Set when the list belongs to a party */
+            party_id?: string;
+            created_by: string;
+            codes: string[];
+            /** Format: date-time */
+            created_at: string;
+        };
+        /**
+         * CustomCodeListSummary
+         * @description Metadata of a custom code list without the codes themselves
+         */
+        CustomCodeListSummary: {
+            list_id: string;
+            name: string;
+            user_id?: string;
+            party_id?: string;
+            created_by: string;
+            /** Format: uint32 */
+            size: number;
+            /** Format: date-time */
+            created_at: string;
+        };
         /** GuestResponse */
         GuestResponse: {
             token: string;
@@ -650,6 +1798,42 @@ export interface components {
             party_secret?: string;
             /** Format: date-time */
             created_at: string;
+            /** Format: date-time */
+            cracked_at?: string;
+            /** @description The code that opened the lock */
+            cracked_code?: string;
+            /**
+             * Format: date-time
+             * @description Archived parties are read-only
+             */
+            archived_at?: string;
+        };
+        /** PartyCodesResponse */
+        PartyCodesResponse: {
+            /**
+             * Format: uint32
+             * @description Total number of codes in the party's sequence
+             */
+            total: number;
+            /** Format: uint32 */
+            offset: number;
+            codes: string[];
+        };
+        /** PartyContribution */
+        PartyContribution: {
+            user_id: string;
+            /**
+             * Format: uint32
+             * @description Number of codes the member submitted, including codes someone else already tried
+             */
+            codes_submitted: number;
+        };
+        /** PartyCrackedRequest */
+        PartyCrackedRequest: {
+            /** @description The code that opened the lock */
+            code: string;
+            /** @default default */
+            lock_id: string;
         };
         /** PartyCreateRequest */
         PartyCreateRequest: Record<string, never>;
@@ -667,6 +1851,16 @@ export interface components {
             data: components["schemas"]["PartyEventData"];
             /** Format: date-time */
             created_at: string;
+            /** @description Key the event was submitted with, lets clients match live events to their own submissions */
+            idempotency_key?: string;
+        };
+        /**
+         * PartyEventArchived
+         * @description Emitted when the party is archived, no events can be submitted after this one
+         */
+        PartyEventArchived: {
+            /** @description Archived by the server because the party went idle or its last member left */
+            automatic: boolean;
         };
         /**
          * PartyEventChatMessage
@@ -675,13 +1869,51 @@ export interface components {
         PartyEventChatMessage: {
             message: string;
         };
+        /**
+         * PartyEventCodeFound
+         * @description Emitted when a member opens the lock, no codes can be submitted after this one
+         */
+        PartyEventCodeFound: {
+            user_id: string;
+            code: string;
+            /**
+             * Format: uint32
+             * @description Position of the code among the distinct codes the party tried on the lock, starting at 1
+             */
+            attempt: number;
+            /** @default default */
+            lock_id: string;
+        };
+        /**
+         * PartyEventCodesAssigned
+         * @description Emitted by the server when a batch of codes is leased to a user
+         *     A new assignment replaces the user's previous lease
+         */
+        PartyEventCodesAssigned: {
+            user_id: string;
+            codes: string[];
+            /** Format: date-time */
+            expires_at: string;
+            /** @default default */
+            lock_id: string;
+        };
         /**
          * PartyEventCodesSubmitted
-         * @description TODO: This event is unimplemented at this time
+         * @description Codes a member tried on a lock
          */
         PartyEventCodesSubmitted: {
+            /**
+             * @description Filled in by the server with the submitting user, any value sent by clients is ignored
+             * @default 
+             */
             user_id: string;
+            /** @description 4 digit PINs, duplicates are dropped */
             codes: string[];
+            /**
+             * @description Lock the codes were tried on
+             * @default default
+             */
+            lock_id: string;
         };
         /**
          * PartyEventCreated
@@ -692,15 +1924,24 @@ export interface components {
         };
         /**
          * PartyEventCursorUpdate
-         * @description TODO: This event is unimplemented at this time
+         * @description Deprecated: clients should request work through `/party/:party_id/assign` instead
          */
         PartyEventCursorUpdate: {
             user_id: string;
             cursor: string;
             /** Format: uint32 */
             size: number;
+            /** @default default */
+            lock_id: string;
         };
-        PartyEventData: components["schemas"]["PartyEventData_PartyEventCreated"] | components["schemas"]["PartyEventData_PartyEventOwnerChanged"] | components["schemas"]["PartyEventData_PartyEventJoinLeave"] | components["schemas"]["PartyEventData_PartyEventCodesSubmitted"] | components["schemas"]["PartyEventData_PartyEventCursorUpdate"] | components["schemas"]["PartyEventData_PartyEventChatMessage"] | components["schemas"]["PartyEventData_PartyEventListOrderChanged"] | components["schemas"]["PartyEventData_PartyEventSettingChanged"];
+        PartyEventData: components["schemas"]["PartyEventData_PartyEventCreated"] | components["schemas"]["PartyEventData_PartyEventOwnerChanged"] | components["schemas"]["PartyEventData_PartyEventJoinLeave"] | components["schemas"]["PartyEventData_PartyEventCodesSubmitted"] | components["schemas"]["PartyEventData_PartyEventCursorUpdate"] | components["schemas"]["PartyEventData_PartyEventCodesAssigned"] | components["schemas"]["PartyEventData_PartyEventChatMessage"] | components["schemas"]["PartyEventData_PartyEventListOrderChanged"] | components["schemas"]["PartyEventData_PartyEventSettingChanged"] | components["schemas"]["PartyEventData_PartyEventSecretRotated"] | components["schemas"]["PartyEventData_PartyEventRoleChanged"] | components["schemas"]["PartyEventData_PartyEventMemberKicked"] | components["schemas"]["PartyEventData_PartyEventLegacySettingChanged"] | components["schemas"]["PartyEventData_PartyEventArchived"] | components["schemas"]["PartyEventData_PartyEventCodeFound"] | components["schemas"]["PartyEventData_PartyEventLockCreated"];
+        PartyEventData_PartyEventArchived: {
+            /**
+             * @example PartyArchived
+             * @enum {string}
+             */
+            type: "PartyArchived";
+        } & components["schemas"]["PartyEventArchived"];
         PartyEventData_PartyEventChatMessage: {
             /**
              * @example PartyChatMessage
@@ -708,6 +1949,20 @@ export interface components {
              */
             type: "PartyChatMessage";
         } & components["schemas"]["PartyEventChatMessage"];
+        PartyEventData_PartyEventCodeFound: {
+            /**
+             * @example PartyCodeFound
+             * @enum {string}
+             */
+            type: "PartyCodeFound";
+        } & components["schemas"]["PartyEventCodeFound"];
+        PartyEventData_PartyEventCodesAssigned: {
+            /**
+             * @example PartyCodesAssigned
+             * @enum {string}
+             */
+            type: "PartyCodesAssigned";
+        } & components["schemas"]["PartyEventCodesAssigned"];
         PartyEventData_PartyEventCodesSubmitted: {
             /**
              * @example PartyCodesSubmitted
@@ -736,6 +1991,13 @@ export interface components {
              */
             type: "PartyJoinLeave";
         } & components["schemas"]["PartyEventJoinLeave"];
+        PartyEventData_PartyEventLegacySettingChanged: {
+            /**
+             * @example PartyLegacySettingChanged
+             * @enum {string}
+             */
+            type: "PartyLegacySettingChanged";
+        } & components["schemas"]["PartyEventLegacySettingChanged"];
         PartyEventData_PartyEventListOrderChanged: {
             /**
              * @example PartyListOrderChanged
@@ -743,6 +2005,20 @@ export interface components {
              */
             type: "PartyListOrderChanged";
         } & components["schemas"]["PartyEventListOrderChanged"];
+        PartyEventData_PartyEventLockCreated: {
+            /**
+             * @example PartyLockCreated
+             * @enum {string}
+             */
+            type: "PartyLockCreated";
+        } & components["schemas"]["PartyEventLockCreated"];
+        PartyEventData_PartyEventMemberKicked: {
+            /**
+             * @example PartyMemberKicked
+             * @enum {string}
+             */
+            type: "PartyMemberKicked";
+        } & components["schemas"]["PartyEventMemberKicked"];
         PartyEventData_PartyEventOwnerChanged: {
             /**
              * @example PartyOwnerChanged
@@ -750,6 +2026,20 @@ export interface components {
              */
             type: "PartyOwnerChanged";
         } & components["schemas"]["PartyEventOwnerChanged"];
+        PartyEventData_PartyEventRoleChanged: {
+            /**
+             * @example PartyRoleChanged
+             * @enum {string}
+             */
+            type: "PartyRoleChanged";
+        } & components["schemas"]["PartyEventRoleChanged"];
+        PartyEventData_PartyEventSecretRotated: {
+            /**
+             * @example PartySecretRotated
+             * @enum {string}
+             */
+            type: "PartySecretRotated";
+        } & components["schemas"]["PartyEventSecretRotated"];
         PartyEventData_PartyEventSettingChanged: {
             /**
              * @example PartySettingChanged
@@ -759,28 +2049,283 @@ export interface components {
         } & components["schemas"]["PartyEventSettingChanged"];
         /**
          * PartyEventJoinLeave
-         * @description TODO: This event is unimplemented at this time
+         * @description Emitted when a user joins or leaves the party
          */
         PartyEventJoinLeave: {
             user_id: string;
             is_join: boolean;
+            /** @description Invite that was redeemed to join, if any */
+            invite_id?: string;
+        };
+        /**
+         * PartyEventLegacySettingChanged
+         * @description A setting stored before settings were typed, these can no longer be submitted
+         */
+        PartyEventLegacySettingChanged: {
+            setting: string;
+            value: unknown;
         };
         /** PartyEventListOrderChanged */
         PartyEventListOrderChanged: {
-            order: unknown[];
+            order: components["schemas"]["CodeListOrderEntry"][];
+        };
+        /**
+         * PartyEventLockCreated
+         * @description Emitted when another code lock is added to the raid
+         */
+        PartyEventLockCreated: {
+            lock_id: string;
+            name: string;
+        };
+        /**
+         * PartyEventMemberKicked
+         * @description Emitted when an owner or moderator removes a member from the party
+         */
+        PartyEventMemberKicked: {
+            user_id: string;
+            /** @description Banned users can not join the party again */
+            ban: boolean;
         };
         /**
          * PartyEventOwnerChanged
-         * @description TODO: This event is unimplemented at this time
+         * @description Emitted when ownership of the party is handed to another member
          */
         PartyEventOwnerChanged: {
             owner_id: string;
         };
-        /** PartyEventSettingChanged */
+        /** PartyEventPage */
+        PartyEventPage: {
+            events: components["schemas"]["PartyEvent"][];
+            /**
+             * Format: int32
+             * @description Id of the last event in the page, pass it again to continue paging
+             */
+            next_cursor?: number;
+            /** @description Whether more events match past this page */
+            has_more: boolean;
+        };
+        /**
+         * PartyEventRoleChanged
+         * @description Emitted when the owner promotes or demotes a member
+         */
+        PartyEventRoleChanged: {
+            user_id: string;
+            role: components["schemas"]["PartyRole"];
+        };
+        /**
+         * PartyEventSecretRotated
+         * @description Emitted when the owner rotates the party secret, the new secret itself is not part of the event
+         */
+        PartyEventSecretRotated: Record<string, never>;
+        /**
+         * PartyEventSettingChanged
+         * @description Emitted when a party setting changes, the shape of `value` depends on `setting`
+         */
         PartyEventSettingChanged: {
-            setting: string;
+            setting: components["schemas"]["PartySettingKey"];
             value: unknown;
         };
+        /**
+         * @description Event types as they appear in the `type` field, used to filter event queries
+         *     Serializes to the key the event is stored under
+         * @enum {string}
+         */
+        PartyEventType: "PartyCreated" | "PartyOwnerChanged" | "PartyJoinLeave" | "PartyCodesSubmitted" | "PartyCursorUpdate" | "PartyCodesAssigned" | "PartyChatMessage" | "PartyListOrderChanged" | "PartySettingChanged" | "PartySecretRotated" | "PartyRoleChanged" | "PartyMemberKicked" | "PartyLegacySettingChanged" | "PartyArchived" | "PartyCodeFound" | "PartyLockCreated";
+        /** @enum {string} */
+        PartyExportFormat: "csv" | "json" | "ndjson";
+        /** @enum {string} */
+        PartyExportKind: "events" | "attempts";
+        /**
+         * PartyFoundCode
+         * @description The code that opened the lock and who found it
+         */
+        PartyFoundCode: {
+            user_id: string;
+            code: string;
+            /**
+             * Format: uint32
+             * @description Number of distinct codes the party tried on the lock up to and including this one
+             *     Unknown for parties that were marked cracked before this was tracked
+             */
+            attempt?: number;
+            /** Format: date-time */
+            found_at: string;
+        };
+        /**
+         * PartyInvite
+         * @description An invite token for a party, optionally limited in time and number of uses
+         */
+        PartyInvite: {
+            invite_id: string;
+            party_id: string;
+            created_by: string;
+            /** Format: date-time */
+            expires_at?: string;
+            /** Format: int32 */
+            max_uses?: number;
+            /** Format: int32 */
+            uses: number;
+            revoked: boolean;
+            /** Format: date-time */
+            created_at: string;
+        };
+        /** PartyInviteCreateRequest */
+        PartyInviteCreateRequest: {
+            /**
+             * Format: date-time
+             * @description When the invite stops working, never if omitted
+             */
+            expires_at?: string;
+            /**
+             * Format: int32
+             * @description How many times the invite can be redeemed, unlimited if omitted
+             */
+            max_uses?: number;
+        };
+        /** PartyListResponse */
+        PartyListResponse: {
+            /**
+             * Format: uint32
+             * @description Total number of parties matching the query
+             */
+            total: number;
+            /** Format: uint32 */
+            offset: number;
+            parties: components["schemas"]["PartySummary"][];
+        };
+        /** PartyLockCreateRequest */
+        PartyLockCreateRequest: {
+            /** @description e.g. "TC room" or "Garage door" */
+            name: string;
+        };
+        /**
+         * PartyLockProgress
+         * @description Overview of a lock as returned by the api
+         */
+        PartyLockProgress: {
+            lock_id: string;
+            name: string;
+            /**
+             * Format: uint32
+             * @description Number of codes in the party's sequence that were tried on this lock
+             */
+            tried: number;
+            /**
+             * Format: uint32
+             * @description Number of codes in the party's sequence
+             */
+            total: number;
+            found?: components["schemas"]["PartyFoundCode"];
+        };
+        /** PartyLockSummary */
+        PartyLockSummary: {
+            lock_id: string;
+            name: string;
+            /**
+             * Format: uint32
+             * @description Number of distinct codes tried on this lock
+             */
+            codes_tried: number;
+            found?: components["schemas"]["PartyFoundCode"];
+        };
+        /**
+         * PartyLockTried
+         * @description Every code tried on a lock, regardless of the party's sequence
+         */
+        PartyLockTried: {
+            lock_id: string;
+            /** @description Base64 of a bitmap over `0000` to `9999`, bit `n % 8` of byte `n / 8` is set when code `n` was tried */
+            tried: string;
+            /**
+             * Format: uint32
+             * @description Number of codes tried
+             */
+            count: number;
+        };
+        /** PartyOwnerRequest */
+        PartyOwnerRequest: {
+            /** @description Member that becomes the new owner */
+            user_id: string;
+        };
+        /** PartyProgress */
+        PartyProgress: {
+            /**
+             * Format: uint32
+             * @description Number of codes in the sequence that were tried so far, summed over all locks
+             */
+            tried: number;
+            /**
+             * Format: uint32
+             * @description Number of codes in the party's sequence times the number of locks
+             */
+            total: number;
+        };
+        /** PartyProgressResponse */
+        PartyProgressResponse: {
+            /**
+             * Format: int32
+             * @description Id of the last event included, pass it as the stream cursor to receive later submissions
+             */
+            event_id: number;
+            locks: components["schemas"]["PartyLockTried"][];
+        };
+        /**
+         * PartyRaidSummary
+         * @description Outcome of a raid
+         */
+        PartyRaidSummary: {
+            party_id: string;
+            /** Format: date-time */
+            started_at: string;
+            /**
+             * Format: int64
+             * @description Seconds from the start of the party until the last lock was opened, or until now if one is still closed
+             */
+            duration_secs: number;
+            /**
+             * Format: uint32
+             * @description Number of distinct codes tried, summed over all locks
+             */
+            codes_tried: number;
+            locks: components["schemas"]["PartyLockSummary"][];
+            /** @description Most active member first */
+            contributions: components["schemas"]["PartyContribution"][];
+        };
+        /**
+         * @description Role of a member within a party
+         * @enum {string}
+         */
+        PartyRole: "owner" | "moderator" | "raider" | "spectator";
+        /** PartyRoleRequest */
+        PartyRoleRequest: {
+            role: components["schemas"]["PartyRole"];
+        };
+        /**
+         * @description Settings a party can change through `PartySettingChanged`
+         * @enum {string}
+         */
+        PartySettingKey: "private" | "steam_only" | "guest_policy" | "location" | "batch_size" | "server_id" | "wipe_time";
+        /**
+         * PartySummary
+         * @description Overview of a party as shown in party listings
+         */
+        PartySummary: {
+            party_id: string;
+            owner_id: string;
+            /** Format: date-time */
+            created_at: string;
+            /** Format: date-time */
+            last_activity: string;
+            /** Format: date-time */
+            cracked_at?: string;
+            /** Format: date-time */
+            archived_at?: string;
+            /** Format: uint32 */
+            member_count: number;
+            /** @description Role of the caller, `None` if they are not a member */
+            role?: components["schemas"]["PartyRole"] & unknown;
+            progress: components["schemas"]["PartyProgress"];
+        };
         /** SCMMTotalInventoryResponse */
         SCMMTotalInventoryResponse: {
             /** Format: uint64 */
@@ -818,8 +2363,8 @@ export interface components {
          *       "name": "John D.",
          *       "avatar_url": "https://avatars.akamai.steamstatic.com/0000000000000000.jpg",
          *       "profile_url": "https://steamcommunity.com/id/john_doe",
-         *       "created_at": "2025-04-09T01:04:16.289611048+00:00",
-         *       "updated_at": "2025-04-09T01:04:16.289614158+00:00"
+         *       "created_at": "2026-10-18T05:27:47.937591670+00:00",
+         *       "updated_at": "2026-10-18T05:27:47.937596989+00:00"
          *     }
          */
         User: {
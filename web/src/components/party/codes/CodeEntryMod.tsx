@@ -76,6 +76,7 @@ const IndividualCodeEntry: FC<{ code: string; party_id: string }> = ({ code, par
                         codes: [code],
                         // TODO: figure out if duplicate (cuz event automatically has author id)
                         user_id: 'deprecated value',
+                        lock_id: 'default',
                     });
                 }}>
                     <LuCheck />